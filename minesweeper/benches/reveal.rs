@@ -0,0 +1,43 @@
+//! Flood fill timings on 1000x1000 boards, run with `cargo bench --bench reveal`.
+
+use std::{hint::black_box, time::Instant};
+
+use minesweeper::minefield::{MineField, Variant, reveal_cells};
+
+const SIZE: usize = 1000;
+const RUNS: u32 = 10;
+
+fn main() {
+    for (name, mines) in [
+        ("empty", 0),
+        ("sparse", SIZE * SIZE / 50),
+        ("beginner density", SIZE * SIZE / 8),
+    ] {
+        let mut total = 0.;
+        let mut opened = 0;
+
+        for _ in 0..RUNS {
+            let mut minefield = MineField::new(SIZE, SIZE, mines, Variant::CLASSIC);
+            // Start from the first cell that cascades, like a lucky first click would
+            let Some((row, column)) = (0..SIZE)
+                .flat_map(|row| (0..SIZE).map(move |column| (row, column)))
+                .find(|&(row, column)| {
+                    let cell = &minefield.cells[row][column];
+                    !cell.is_mined() && cell.mined_neighbours == 0
+                })
+            else {
+                continue;
+            };
+
+            let start = Instant::now();
+            opened += black_box(reveal_cells(&mut minefield, column, row)).len();
+            total += start.elapsed().as_secs_f64();
+        }
+
+        println!(
+            "{name:>16}: {:8.3} ms per reveal, {} cells opened on average",
+            total * 1000. / RUNS as f64,
+            opened / RUNS as usize,
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::ConfigDir, minefield::MineField};
+
+/// Colours numbers get when drawn as text.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Palette {
+    /// The skin's own number images, or the classic colours once numbers are text.
+    #[default]
+    Skin,
+    /// Every number black on an untinted cell, so only the digit's shape matters.
+    HighContrast,
+    /// Okabe and Ito's colours, told apart with any kind of colour blindness.
+    ColourBlind,
+}
+
+impl Palette {
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Skin => "Skin",
+            Palette::HighContrast => "High contrast",
+            Palette::ColourBlind => "Colour blind",
+        }
+    }
+
+    pub fn next(self) -> Palette {
+        match self {
+            Palette::Skin => Palette::HighContrast,
+            Palette::HighContrast => Palette::ColourBlind,
+            Palette::ColourBlind => Palette::Skin,
+        }
+    }
+
+    /// Colour of a positive number, counts past 8 reuse the colours from 1.
+    pub fn number_colour(self, number: i32) -> Color {
+        let colours = match self {
+            Palette::Skin => [
+                Color::srgb_u8(0, 0, 255),
+                Color::srgb_u8(0, 128, 0),
+                Color::srgb_u8(255, 0, 0),
+                Color::srgb_u8(0, 0, 128),
+                Color::srgb_u8(128, 0, 0),
+                Color::srgb_u8(0, 128, 128),
+                Color::srgb_u8(0, 0, 0),
+                Color::srgb_u8(128, 128, 128),
+            ],
+            Palette::HighContrast => [Color::BLACK; 8],
+            Palette::ColourBlind => [
+                Color::srgb_u8(0, 114, 178),
+                Color::srgb_u8(0, 158, 115),
+                Color::srgb_u8(213, 94, 0),
+                Color::srgb_u8(204, 121, 167),
+                Color::srgb_u8(230, 159, 0),
+                Color::srgb_u8(86, 180, 233),
+                Color::srgb_u8(0, 0, 0),
+                Color::srgb_u8(128, 128, 128),
+            ],
+        };
+        colours[(number.max(1) as usize - 1) % colours.len()]
+    }
+
+    /// What cell sprites get multiplied by, in place of the skin's tint.
+    pub fn tint(self, skin_tint: Color) -> Color {
+        match self {
+            Palette::HighContrast => Color::WHITE,
+            _ => skin_tint,
+        }
+    }
+}
+
+/// Saved to `accessibility.ron` in the config dir. C cycles the palettes, T switches text
+/// numbers and N announcements.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    pub palette: Palette,
+    /// Numbers drawn as text instead of the skin's images.
+    pub text_numbers: bool,
+    /// Prints every `Announcement` to stdout.
+    pub announce: bool,
+}
+
+impl AccessibilitySettings {
+    /// The skin's images carry their own colours, so any other palette needs text.
+    pub fn numbers_as_text(&self) -> bool {
+        self.text_numbers || self.palette != Palette::Skin
+    }
+}
+
+/// What just happened in words, written by gameplay systems next to their `SoundEffect`.
+/// Each one is a line of stdout for screen readers and other assistive tools to pick up.
+#[derive(Event)]
+pub struct Announcement(pub String);
+
+/// Like "row 3 column 5: 2", counting from 1 at the top left as the board is seen.
+pub fn describe_cell(minefield: &MineField, row: usize, column: usize) -> String {
+    let cell = &minefield.cells[row][column];
+    let content = match (cell.is_open, cell.is_mined()) {
+        (true, true) if cell.mines < 0 => "anti-mine".to_string(),
+        (true, true) => "mine".to_string(),
+        (true, false) if cell.mines_around == 0 => "empty".to_string(),
+        (true, false) => cell.mines_around.to_string(),
+        (false, _) if cell.is_tagged() => "flagged".to_string(),
+        (false, _) => "hidden".to_string(),
+    };
+    format!(
+        "row {} column {}: {content}",
+        minefield.height - row,
+        column + 1
+    )
+}
+
+pub fn announce(
+    mut announcements: EventReader<Announcement>,
+    settings: Res<AccessibilitySettings>,
+) {
+    for announcement in announcements.read() {
+        if settings.announce {
+            println!("{}", announcement.0);
+        }
+    }
+}
+
+pub fn accessibility_controls(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<AccessibilitySettings>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyC) {
+        settings.palette = settings.palette.next();
+    }
+    if keyboard.just_pressed(KeyCode::KeyT) {
+        settings.text_numbers = !settings.text_numbers;
+    }
+    if keyboard.just_pressed(KeyCode::KeyN) {
+        settings.announce = !settings.announce;
+    }
+}
+
+pub fn save_accessibility_settings(
+    settings: Res<AccessibilitySettings>,
+    config: Res<ConfigDir>,
+) {
+    config.save("accessibility", &*settings);
+}
@@ -0,0 +1,290 @@
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BoardSource, NewGame, Outcome,
+    accessibility::Announcement,
+    classic,
+    config::{self, ConfigDir},
+    events::{CellOpened, ChordPerformed, FlagToggled, GameEnded},
+    minefield::MineField,
+    open_cells, place_flags, solver,
+};
+
+const TOAST_SECONDS: f32 = 4.;
+const TOAST_COLOR: Color = Color::srgb(0.15, 0.3, 0.15);
+/// Work the win frame may spend playing the board through for `NoGuessWin`.
+const MAX_NO_GUESS_STEPS: usize = 1 << 22;
+
+/// What a finished game has to have been for an achievement to be earned by it.
+#[derive(Clone, Copy, Debug)]
+pub enum Condition {
+    /// Any board cleared.
+    Win,
+    /// A board of at least `cells` cells and `mines` mines cleared in under `seconds`.
+    FastWin {
+        cells: usize,
+        mines: usize,
+        seconds: f32,
+    },
+    /// Cleared without a flag ever being placed.
+    WinWithoutFlags,
+    /// This many boards cleared in a row.
+    WinStreak(u32),
+    /// Cleared a board that never needed a guess from the first click on. The game gives
+    /// no hints, so every such win is one without them. Boards too big to play through
+    /// quickly don't count.
+    NoGuessWin,
+    /// Cleared with only the first cell clicked open, everything after by chording.
+    ChordOnlyWin,
+}
+
+pub struct Achievement {
+    /// Kept in `achievements.ron`, so never changed once released.
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub condition: Condition,
+}
+
+pub const ACHIEVEMENTS: [Achievement; 6] = [
+    Achievement {
+        id: "first-win",
+        name: "First sweep",
+        description: "Clear a board",
+        condition: Condition::Win,
+    },
+    Achievement {
+        id: "expert-200",
+        name: "Expert pace",
+        description: "Clear an expert board, 30 by 16 with 99 mines, in under 200 s",
+        condition: Condition::FastWin {
+            cells: 30 * 16,
+            mines: 99,
+            seconds: 200.,
+        },
+    },
+    Achievement {
+        id: "no-flags",
+        name: "No flags needed",
+        description: "Clear a board without placing a flag",
+        condition: Condition::WinWithoutFlags,
+    },
+    Achievement {
+        id: "streak-10",
+        name: "Unbroken",
+        description: "Clear 10 boards in a row",
+        condition: Condition::WinStreak(10),
+    },
+    Achievement {
+        id: "no-guess",
+        name: "Pure logic",
+        description: "Clear a board that never needs a guess, with no hints",
+        condition: Condition::NoGuessWin,
+    },
+    Achievement {
+        id: "chord-only",
+        name: "Chord master",
+        description: "Clear a board opening everything after the first click by chording",
+        condition: Condition::ChordOnlyWin,
+    },
+];
+
+/// Achievements earned, saved to `achievements.ron` in the config dir.
+#[derive(Resource, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Achievements {
+    /// Keyed by `Achievement::id`.
+    unlocked: BTreeSet<String>,
+    /// Boards cleared in a row, a loss starts it again from nothing.
+    pub streak: u32,
+}
+
+impl Achievements {
+    pub fn is_unlocked(&self, achievement: &Achievement) -> bool {
+        self.unlocked.contains(achievement.id)
+    }
+}
+
+/// How the board being played has been played so far, reset by every new board.
+#[derive(Resource, Default)]
+struct GameStats {
+    /// Where the first click opened the board.
+    first_click: Option<(usize, usize)>,
+    clicks: u32,
+    chords: u32,
+    flagged: bool,
+}
+
+impl GameStats {
+    fn meets(
+        &self,
+        condition: Condition,
+        minefield: &MineField,
+        time: f32,
+        streak: u32,
+    ) -> bool {
+        match condition {
+            Condition::Win => true,
+            Condition::FastWin {
+                cells,
+                mines,
+                seconds,
+            } => {
+                let mined = minefield
+                    .cells
+                    .iter()
+                    .flatten()
+                    .filter(|cell| cell.is_mined());
+                minefield.width * minefield.height >= cells
+                    && mined.count() >= mines
+                    && time < seconds
+            }
+            Condition::WinWithoutFlags => !self.flagged,
+            Condition::WinStreak(wins) => streak >= wins,
+            Condition::NoGuessWin => self.first_click.is_some_and(|start| {
+                solver::guesses_needed(minefield, start, MAX_NO_GUESS_STEPS)
+                    .is_some_and(|guesses| guesses.is_empty())
+            }),
+            Condition::ChordOnlyWin => self.clicks == 1 && self.chords > 0,
+        }
+    }
+}
+
+/// A notice of a newly earned achievement, gone after `TOAST_SECONDS`.
+#[derive(Component)]
+struct Toast(Timer);
+
+pub fn plugin(app: &mut App) {
+    let achievements = config::load::<Achievements>(app, "achievements");
+    app.init_resource::<GameStats>()
+        .insert_resource(achievements)
+        .add_systems(
+            Update,
+            (
+                (
+                    track_moves.after(open_cells).after(place_flags),
+                    unlock_achievements,
+                )
+                    .chain()
+                    .run_if(classic),
+                expire_toasts,
+            ),
+        );
+}
+
+fn track_moves(
+    mut new_game: EventReader<NewGame>,
+    mut cells_opened: EventReader<CellOpened>,
+    mut chords: EventReader<ChordPerformed>,
+    mut flags_toggled: EventReader<FlagToggled>,
+    mut stats: ResMut<GameStats>,
+) {
+    if new_game.read().count() != 0 {
+        *stats = GameStats::default();
+    }
+    for opened in cells_opened.read() {
+        stats.first_click.get_or_insert((opened.row, opened.column));
+        stats.clicks += 1;
+    }
+    stats.chords += chords.read().count() as u32;
+    if flags_toggled.read().any(|flag| flag.flags != 0) {
+        stats.flagged = true;
+    }
+}
+
+/// Weighs every finished game against the achievements not yet earned. Only boards dealt
+/// at random count, a board out of the editor could be made to earn anything.
+fn unlock_achievements(
+    mut cmd: Commands,
+    mut ended: EventReader<GameEnded>,
+    board_source: Res<BoardSource>,
+    stats: Res<GameStats>,
+    mut achievements: ResMut<Achievements>,
+    config: Res<ConfigDir>,
+    minefield_query: Query<&MineField>,
+    toasts: Query<(), With<Toast>>,
+    mut announcements: EventWriter<Announcement>,
+) {
+    let Ok(minefield) = minefield_query.single() else {
+        return;
+    };
+    if !matches!(*board_source, BoardSource::Random) {
+        ended.clear();
+        return;
+    }
+
+    for ended in ended.read() {
+        if ended.outcome == Outcome::Lost {
+            achievements.streak = 0;
+            config.save("achievements", &*achievements);
+            continue;
+        }
+        achievements.streak += 1;
+
+        let earned: Vec<&Achievement> = ACHIEVEMENTS
+            .iter()
+            .filter(|achievement| !achievements.is_unlocked(achievement))
+            .filter(|achievement| {
+                stats.meets(
+                    achievement.condition,
+                    minefield,
+                    ended.time,
+                    achievements.streak,
+                )
+            })
+            .collect();
+        for (index, achievement) in earned.into_iter().enumerate() {
+            achievements.unlocked.insert(achievement.id.to_string());
+            announcements.write(Announcement(format!(
+                "Achievement earned, {}",
+                achievement.name
+            )));
+            spawn_toast(&mut cmd, achievement, toasts.iter().count() + index);
+        }
+        config.save("achievements", &*achievements);
+    }
+}
+
+/// Toasts stack down from the top of the screen, over whatever screen is showing.
+fn spawn_toast(cmd: &mut Commands, achievement: &Achievement, slot: usize) {
+    cmd.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(16. + slot as f32 * 64.),
+            right: Val::Px(16.),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::axes(Val::Px(16.), Val::Px(8.)),
+            ..default()
+        },
+        BackgroundColor(TOAST_COLOR),
+        GlobalZIndex(10),
+        Toast(Timer::from_seconds(TOAST_SECONDS, TimerMode::Once)),
+    ))
+    .with_children(|toast| {
+        toast.spawn((
+            Text::new(achievement.name),
+            TextFont {
+                font_size: 20.,
+                ..default()
+            },
+        ));
+        toast.spawn((
+            Text::new(achievement.description),
+            TextFont {
+                font_size: 14.,
+                ..default()
+            },
+        ));
+    });
+}
+
+fn expire_toasts(mut cmd: Commands, time: Res<Time>, mut toasts: Query<(Entity, &mut Toast)>) {
+    for (entity, mut toast) in &mut toasts {
+        if toast.0.tick(time.delta()).finished() {
+            cmd.entity(entity).despawn();
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::CameraView;
+
+/// Delay between rings of a cascade, measured in cells from the clicked one.
+const RIPPLE_DELAY: f32 = 0.03;
+const POP_DURATION: f32 = 0.15;
+const PARTICLES_AMOUNT: usize = 40;
+const PARTICLE_LIFETIME: f32 = 0.8;
+const SHAKE_DECAY: f32 = 1.5;
+const SHAKE_STRENGTH: f32 = 12.;
+
+/// Toggled with A, when off every change shows up on the very frame it happens.
+#[derive(Resource)]
+pub struct AnimationSettings {
+    pub enabled: bool,
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// When cells were opened or flagged, in seconds of `Time::elapsed_secs`.
+#[derive(Resource, Default)]
+pub struct CellAnimations {
+    revealed_at: HashMap<(usize, usize), f32>,
+    flagged_at: HashMap<(usize, usize), f32>,
+    /// The mine that ended the game.
+    pub exploded: Option<(usize, usize)>,
+}
+
+impl CellAnimations {
+    /// Schedules `opened` cells to appear ring by ring around `origin`.
+    pub fn ripple(&mut self, origin: (usize, usize), opened: &[(usize, usize)], now: f32) {
+        for &(row, column) in opened {
+            let distance = row.abs_diff(origin.0).max(column.abs_diff(origin.1));
+            self.revealed_at
+                .insert((row, column), now + distance as f32 * RIPPLE_DELAY);
+        }
+    }
+
+    pub fn flag(&mut self, cell: (usize, usize), now: f32) {
+        self.flagged_at.insert(cell, now);
+    }
+
+    /// Jumps every pending reveal to `now`, so a new click never waits on the last one.
+    pub fn skip(&mut self, now: f32) {
+        for time in self.revealed_at.values_mut() {
+            *time = time.min(now);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Whether an open cell should still be drawn hidden while its ring catches up.
+    pub fn is_pending(&self, cell: (usize, usize), now: f32) -> bool {
+        self.revealed_at.get(&cell).is_some_and(|&time| time > now)
+    }
+
+    /// Scale of an open cell's sprite, growing to 1 right after it is revealed.
+    pub fn reveal_scale(&self, cell: (usize, usize), now: f32) -> f32 {
+        pop_scale(self.revealed_at.get(&cell), now)
+    }
+
+    /// Scale of a flag's sprite, growing to 1 right after it is placed.
+    pub fn flag_scale(&self, cell: (usize, usize), now: f32) -> f32 {
+        pop_scale(self.flagged_at.get(&cell), now)
+    }
+}
+
+fn pop_scale(start: Option<&f32>, now: f32) -> f32 {
+    let Some(&start) = start else {
+        return 1.;
+    };
+    let progress = ((now - start) / POP_DURATION).clamp(0., 1.);
+    // Overshoots a little before settling
+    0.6 + 0.4 * progress + 0.15 * (progress * std::f32::consts::PI).sin()
+}
+
+/// Camera shake strength, decays back to 0 on its own.
+#[derive(Resource, Default)]
+pub struct ScreenShake {
+    trauma: f32,
+}
+
+#[derive(Component)]
+pub struct Particle {
+    velocity: Vec2,
+    lifetime: Timer,
+}
+
+pub fn toggle_animations(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    mut settings: ResMut<AnimationSettings>,
+    mut animations: ResMut<CellAnimations>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyA) {
+        settings.enabled = !settings.enabled;
+        animations.skip(time.elapsed_secs());
+    }
+}
+
+/// Sends debris flying from `position` and shakes the camera.
+pub fn explode(cmd: &mut Commands, shake: &mut ScreenShake, position: Vec2) {
+    for _ in 0..PARTICLES_AMOUNT {
+        let angle = rand::random_range(0.0..std::f32::consts::TAU);
+        let speed = rand::random_range(100.0..400.0);
+        let color = Color::srgb(1., rand::random_range(0.2..0.8), 0.);
+
+        cmd.spawn((
+            Sprite {
+                color,
+                custom_size: Some(Vec2::splat(rand::random_range(4.0..10.0))),
+                ..default()
+            },
+            Transform::from_translation(position.extend(3.)),
+            Particle {
+                velocity: Vec2::from_angle(angle) * speed,
+                lifetime: Timer::from_seconds(PARTICLE_LIFETIME, TimerMode::Once),
+            },
+        ));
+    }
+    shake.trauma = 1.;
+}
+
+pub fn update_particles(
+    mut cmd: Commands,
+    time: Res<Time>,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+    for (entity, mut particle, mut transform, mut sprite) in &mut particles {
+        particle.lifetime.tick(time.delta());
+        if particle.lifetime.finished() {
+            cmd.entity(entity).despawn();
+            continue;
+        }
+
+        particle.velocity.y -= 600. * time.delta_secs();
+        transform.translation += (particle.velocity * time.delta_secs()).extend(0.);
+        sprite
+            .color
+            .set_alpha(particle.lifetime.fraction_remaining());
+    }
+}
+
+/// Also where the camera's pan from `CameraView` gets applied.
+pub fn shake_camera(
+    time: Res<Time>,
+    view: Res<CameraView>,
+    mut shake: ResMut<ScreenShake>,
+    mut camera: Query<&mut Transform, With<Camera2d>>,
+) {
+    let Ok(mut transform) = camera.single_mut() else {
+        return;
+    };
+
+    shake.trauma = (shake.trauma - SHAKE_DECAY * time.delta_secs()).max(0.);
+    let offset = shake.trauma * shake.trauma * SHAKE_STRENGTH;
+    transform.translation.x = view.pan.x + rand::random_range(-1.0..=1.0) * offset;
+    transform.translation.y = view.pan.y + rand::random_range(-1.0..=1.0) * offset;
+}
@@ -0,0 +1,131 @@
+use bevy::{audio::Volume, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::config::ConfigDir;
+
+pub const VOLUME_STEP: f32 = 0.1;
+
+/// Played through `play_sounds`, gameplay systems only say what happened.
+#[derive(Event, Clone, Copy)]
+pub enum SoundEffect {
+    Open,
+    Cascade,
+    Flag,
+    Chord,
+    Explosion,
+    Win,
+}
+
+#[derive(Resource)]
+pub struct SoundEffects {
+    open: Handle<AudioSource>,
+    cascade: Handle<AudioSource>,
+    flag: Handle<AudioSource>,
+    chord: Handle<AudioSource>,
+    explosion: Handle<AudioSource>,
+    win: Handle<AudioSource>,
+}
+
+/// Saved to `audio.ron` in the config dir. M mutes, -/= change the master volume and
+/// [/] the effects volume.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub effects: f32,
+    pub muted: bool,
+}
+
+impl AudioSettings {
+    pub fn change_master(&mut self, delta: f32) {
+        self.master = (self.master + delta).clamp(0., 1.);
+    }
+
+    pub fn change_effects(&mut self, delta: f32) {
+        self.effects = (self.effects + delta).clamp(0., 1.);
+    }
+
+    fn effects_volume(&self) -> f32 {
+        if self.muted {
+            0.
+        } else {
+            self.master * self.effects
+        }
+    }
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: 1.,
+            effects: 0.8,
+            muted: false,
+        }
+    }
+}
+
+pub fn setup_sounds(mut cmd: Commands, assets: Res<AssetServer>) {
+    cmd.insert_resource(SoundEffects {
+        open: assets.load("sounds/open.wav"),
+        cascade: assets.load("sounds/cascade.wav"),
+        flag: assets.load("sounds/flag.wav"),
+        chord: assets.load("sounds/chord.wav"),
+        explosion: assets.load("sounds/explosion.wav"),
+        win: assets.load("sounds/win.wav"),
+    });
+}
+
+/// Without an audio device Bevy only warns and the players spawned here stay silent.
+pub fn play_sounds(
+    mut cmd: Commands,
+    mut events: EventReader<SoundEffect>,
+    sounds: Res<SoundEffects>,
+    settings: Res<AudioSettings>,
+) {
+    let volume = settings.effects_volume();
+
+    for effect in events.read() {
+        if volume <= 0. {
+            continue;
+        }
+
+        let sound = match effect {
+            SoundEffect::Open => &sounds.open,
+            SoundEffect::Cascade => &sounds.cascade,
+            SoundEffect::Flag => &sounds.flag,
+            SoundEffect::Chord => &sounds.chord,
+            SoundEffect::Explosion => &sounds.explosion,
+            SoundEffect::Win => &sounds.win,
+        };
+        cmd.spawn((
+            AudioPlayer(sound.clone()),
+            PlaybackSettings::DESPAWN.with_volume(Volume::Linear(volume)),
+        ));
+    }
+}
+
+pub fn audio_controls(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<AudioSettings>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyM) {
+        settings.muted = !settings.muted;
+    }
+    if keyboard.just_pressed(KeyCode::Minus) {
+        settings.change_master(-VOLUME_STEP);
+    }
+    if keyboard.just_pressed(KeyCode::Equal) {
+        settings.change_master(VOLUME_STEP);
+    }
+    if keyboard.just_pressed(KeyCode::BracketLeft) {
+        settings.change_effects(-VOLUME_STEP);
+    }
+    if keyboard.just_pressed(KeyCode::BracketRight) {
+        settings.change_effects(VOLUME_STEP);
+    }
+}
+
+/// Runs whenever the settings change, be it from the keyboard or the settings screen.
+pub fn save_audio_settings(settings: Res<AudioSettings>, config: Res<ConfigDir>) {
+    config.save("audio", &*settings);
+}
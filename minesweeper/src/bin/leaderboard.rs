@@ -0,0 +1,209 @@
+//! A small leaderboard the game posts its won games to. Every submission is replayed on
+//! the board its seed makes before it's let in, and the tables are kept in a RON file.
+//!
+//! Run with `cargo run --bin leaderboard -- --secret <secret>`, then set the same
+//! secret and `server: Some("http://127.0.0.1:7878")` in the game's `leaderboard.ron`.
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    time::Duration,
+};
+
+use minesweeper::leaderboard::{Entry, Submission, TOP_ENTRIES, Tables};
+
+const USAGE: &str = "usage: leaderboard [--address <host:port>] [--data <file.ron>] \
+[--secret <secret>]
+
+The secret may also come from MINESWEEPER_LEADERBOARD_SECRET.";
+/// Largest request body read, far more than a game on the biggest board needs.
+const MAX_BODY: usize = 4 << 20;
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Options {
+    address: String,
+    data: PathBuf,
+    secret: String,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            address: "127.0.0.1:7878".to_string(),
+            data: PathBuf::from("leaderboard.ron"),
+            secret: std::env::var("MINESWEEPER_LEADERBOARD_SECRET").unwrap_or_default(),
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().cloned().ok_or(format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--address" => options.address = value()?,
+                "--data" => options.data = PathBuf::from(value()?),
+                "--secret" => options.secret = value()?,
+                _ => return Err(format!("unknown argument {arg}")),
+            }
+        }
+
+        if options.secret.is_empty() {
+            return Err("a secret is needed to check signatures".to_string());
+        }
+        Ok(options)
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    body: String,
+}
+
+struct Response {
+    status: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn new(status: &'static str, body: impl Into<String>) -> Response {
+        Response {
+            status,
+            body: body.into(),
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(err) = Options::parse(&args).and_then(|options| serve(&options)) {
+        eprintln!("{err}\n\n{USAGE}");
+        std::process::exit(1);
+    }
+}
+
+fn serve(options: &Options) -> Result<(), String> {
+    let mut tables: Tables = match fs::read_to_string(&options.data) {
+        Ok(contents) => ron::from_str(&contents)
+            .map_err(|err| format!("can't read {}: {err}", options.data.display()))?,
+        Err(_) => Tables::new(),
+    };
+    let listener = TcpListener::bind(&options.address)
+        .map_err(|err| format!("can't listen on {}: {err}", options.address))?;
+    println!("Serving scores on http://{}", options.address);
+
+    // One connection at a time, a request takes far less than any player notices
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+        let response = match read_request(&mut stream) {
+            Ok(request) => handle(&request, &mut tables, options),
+            Err(err) => Response::new("400 Bad Request", err),
+        };
+        let _ = write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=utf-8\r\n\
+Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            response.status,
+            response.body.len(),
+            response.body
+        );
+    }
+    Ok(())
+}
+
+fn read_request(stream: &mut TcpStream) -> Result<Request, String> {
+    stream
+        .set_read_timeout(Some(TIMEOUT))
+        .and_then(|()| stream.set_write_timeout(Some(TIMEOUT)))
+        .map_err(|err| err.to_string())?;
+    let mut reader = BufReader::new(stream.take(MAX_BODY as u64 + 8192));
+
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|err| err.to_string())?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err("malformed request line".to_string());
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut length = 0;
+    loop {
+        line.clear();
+        reader.read_line(&mut line).map_err(|err| err.to_string())?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse().map_err(|_| "bad content length")?;
+        }
+    }
+    if length > MAX_BODY {
+        return Err("body too large".to_string());
+    }
+
+    let mut body = vec![0; length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|err| err.to_string())?;
+    let body = String::from_utf8(body).map_err(|_| "body isn't UTF-8")?;
+    Ok(Request { method, path, body })
+}
+
+fn handle(request: &Request, tables: &mut Tables, options: &Options) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/scores") => match ron::to_string(tables) {
+            Ok(body) => Response::new("200 OK", body),
+            Err(err) => Response::new("500 Internal Server Error", err.to_string()),
+        },
+        ("POST", "/scores") => submit(&request.body, tables, options),
+        (_, "/scores") => Response::new("405 Method Not Allowed", "use GET or POST"),
+        _ => Response::new("404 Not Found", "only /scores is served"),
+    }
+}
+
+/// Checks the game and adds it to its table, answering with its place.
+fn submit(body: &str, tables: &mut Tables, options: &Options) -> Response {
+    let submission: Submission = match ron::from_str(body) {
+        Ok(submission) => submission,
+        Err(err) => return Response::new("400 Bad Request", err.to_string()),
+    };
+    if !submission.is_signed_with(&options.secret) {
+        return Response::new("401 Unauthorized", "bad signature");
+    }
+    let game = submission.game;
+    if let Err(err) = game.verify() {
+        return Response::new("422 Unprocessable Entity", err);
+    }
+
+    let table = tables.entry(game.difficulty()).or_default();
+    if table.iter().any(|entry| entry.seed == game.seed) {
+        return Response::new("409 Conflict", "this board was already submitted");
+    }
+    table.push(Entry {
+        name: game.name,
+        time: game.time,
+        seed: game.seed,
+    });
+    table.sort_by(|a, b| a.time.total_cmp(&b.time));
+    table.truncate(TOP_ENTRIES);
+    let place = table.iter().position(|entry| entry.seed == game.seed);
+
+    let saved = ron::ser::to_string_pretty(tables, Default::default())
+        .map_err(|err| err.to_string())
+        .and_then(|contents| {
+            fs::write(&options.data, contents).map_err(|err| err.to_string())
+        });
+    if let Err(err) = saved {
+        eprintln!("Couldn't save {}: {err}", options.data.display());
+    }
+
+    match place {
+        Some(place) => Response::new("200 OK", format!("{}", place + 1)),
+        None => Response::new("200 OK", "-"),
+    }
+}
@@ -0,0 +1,287 @@
+use bevy::{prelude::*, time::Stopwatch};
+
+use crate::{
+    BoardSettings, BoardSource, ControlPanelFace, GameStates, NewGame, Outcome, PauseState,
+    accessibility::Announcement,
+    audio::SoundEffect,
+    config::ConfigDir,
+    draw_control_panel,
+    events::MineHit,
+    minefield::{MineField, check_win_condition, reveal_all_mines},
+    open_cells,
+    post_mortem::LossReport,
+    records::Records,
+    start_new_game,
+};
+
+const SURVIVAL_LIVES: u32 = 3;
+/// Mines added to each survival board over the one before.
+const SURVIVAL_EXTRA_MINES: usize = 2;
+/// Survival boards stop getting denser once this share of their cells is mined.
+const SURVIVAL_MAX_DENSITY: f32 = 0.3;
+const TIME_ATTACK_SECONDS: f32 = 120.;
+/// What time attack deals, whatever size random boards are set to.
+const BEGINNER: BoardSettings = BoardSettings {
+    width: 9,
+    height: 9,
+    mines: 10,
+};
+const STATUS_COLOR: Color = Color::srgb(1., 0.8, 0.2);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChallengeMode {
+    /// Boards get denser one after another until the lives run out.
+    Survival,
+    /// As many beginner boards as can be cleared before the time is up.
+    TimeAttack,
+}
+
+impl ChallengeMode {
+    pub const ALL: [ChallengeMode; 2] = [ChallengeMode::Survival, ChallengeMode::TimeAttack];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ChallengeMode::Survival => "Survival",
+            ChallengeMode::TimeAttack => "Time attack",
+        }
+    }
+}
+
+/// A run of boards dealt one after another. Without a mode the game is one board at a time.
+#[derive(Resource, Default)]
+pub struct Challenge {
+    pub mode: Option<ChallengeMode>,
+    pub cleared: u32,
+    pub lives: u32,
+    /// Time across every board of the run, only ticks while playing.
+    pub elapsed: Stopwatch,
+    /// The run just ended cleared more boards than any before it.
+    pub new_record: bool,
+}
+
+impl Challenge {
+    pub fn start(&mut self, mode: ChallengeMode) {
+        *self = Challenge {
+            mode: Some(mode),
+            lives: SURVIVAL_LIVES,
+            ..default()
+        };
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.mode.is_some()
+    }
+
+    /// The next board to deal, survival adding mines to the size random boards are set to.
+    fn board(&self, board: &BoardSettings) -> BoardSettings {
+        match self.mode {
+            Some(ChallengeMode::Survival) => {
+                let cells = board.width * board.height;
+                let densest =
+                    ((cells as f32 * SURVIVAL_MAX_DENSITY) as usize).max(board.mines);
+                let mines = board.mines + self.cleared as usize * SURVIVAL_EXTRA_MINES;
+                BoardSettings {
+                    mines: mines.min(densest),
+                    ..*board
+                }
+            }
+            Some(ChallengeMode::TimeAttack) => BEGINNER,
+            None => *board,
+        }
+    }
+
+    fn time_left(&self) -> f32 {
+        (TIME_ATTACK_SECONDS - self.elapsed.elapsed_secs()).max(0.)
+    }
+
+    /// How the run went, for the game over screen.
+    pub fn summary(&self) -> String {
+        let summary = match self.mode {
+            Some(ChallengeMode::Survival) => format!("Survived {} boards", self.cleared),
+            Some(ChallengeMode::TimeAttack) => {
+                format!("{} boards in {TIME_ATTACK_SECONDS:.0} s", self.cleared)
+            }
+            None => String::new(),
+        };
+        if self.new_record {
+            summary + ", a new best"
+        } else {
+            summary
+        }
+    }
+
+    /// Keeps the run's result, returns whether it's a new best.
+    fn finish(&mut self, records: &mut Records, config: &ConfigDir) -> bool {
+        if let Some(mode) = self.mode {
+            self.new_record = records.submit_challenge(mode, self.cleared, config);
+        }
+        self.new_record
+    }
+}
+
+/// A challenge is being played, so boards come from it and clearing one isn't a win.
+pub fn active(challenge: Res<Challenge>) -> bool {
+    challenge.is_active()
+}
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<Challenge>()
+        .add_systems(OnEnter(GameStates::MainMenu), leave_challenge)
+        .add_systems(
+            Update,
+            (
+                (lose_lives, deal_next_board)
+                    .chain()
+                    .after(open_cells)
+                    .run_if(in_state(PauseState::Running)),
+                tick_challenge.run_if(in_state(PauseState::Running)),
+                deal_boards,
+            )
+                .chain()
+                .before(start_new_game)
+                .run_if(active),
+        )
+        .add_systems(
+            Update,
+            draw_status.after(draw_control_panel).run_if(
+                in_state(PauseState::Running)
+                    .or(in_state(GameStates::GameOver))
+                    .and(active),
+            ),
+        );
+}
+
+fn leave_challenge(mut challenge: ResMut<Challenge>) {
+    *challenge = Challenge::default();
+}
+
+/// Survival takes a life for every mine, the last one ends the run. Time attack throws the
+/// board away for a fresh one.
+fn lose_lives(
+    mut mines_hit: EventReader<MineHit>,
+    mut challenge: ResMut<Challenge>,
+    mut records: ResMut<Records>,
+    config: Res<ConfigDir>,
+    mut minefield_query: Query<&mut MineField>,
+    mut outcome: ResMut<Outcome>,
+    mut loss_report: ResMut<LossReport>,
+    mut next_state: ResMut<NextState<GameStates>>,
+    mut new_game: EventWriter<NewGame>,
+    mut announcements: EventWriter<Announcement>,
+) {
+    if mines_hit.read().count() == 0 {
+        return;
+    }
+
+    match challenge.mode {
+        Some(ChallengeMode::Survival) => {
+            challenge.lives = challenge.lives.saturating_sub(1);
+            if challenge.lives > 0 {
+                announcements.write(Announcement(format!("{} lives left", challenge.lives)));
+                return;
+            }
+            if let Ok(mut minefield) = minefield_query.single_mut() {
+                reveal_all_mines(&mut minefield);
+            }
+            challenge.finish(&mut records, &config);
+            loss_report.0 = None;
+            *outcome = Outcome::Lost;
+            next_state.set(GameStates::GameOver);
+        }
+        Some(ChallengeMode::TimeAttack) => {
+            new_game.write(NewGame);
+        }
+        None => {}
+    }
+}
+
+/// A cleared board counts towards the run and the next one is dealt straight away.
+fn deal_next_board(
+    mut challenge: ResMut<Challenge>,
+    minefield_query: Query<&MineField>,
+    next_state: Res<NextState<GameStates>>,
+    mut new_game: EventWriter<NewGame>,
+    mut sounds: EventWriter<SoundEffect>,
+    mut announcements: EventWriter<Announcement>,
+) {
+    let Ok(minefield) = minefield_query.single() else {
+        return;
+    };
+    if matches!(*next_state, NextState::Pending(GameStates::GameOver))
+        || !check_win_condition(minefield)
+    {
+        return;
+    }
+
+    challenge.cleared += 1;
+    sounds.write(SoundEffect::Win);
+    announcements.write(Announcement(format!("Board {} cleared", challenge.cleared)));
+    new_game.write(NewGame);
+}
+
+/// Time attack ends when the clock runs out, with however many boards were cleared by then.
+fn tick_challenge(
+    time: Res<Time>,
+    mut challenge: ResMut<Challenge>,
+    mut records: ResMut<Records>,
+    config: Res<ConfigDir>,
+    mut outcome: ResMut<Outcome>,
+    mut next_state: ResMut<NextState<GameStates>>,
+    mut announcements: EventWriter<Announcement>,
+) {
+    challenge.elapsed.tick(time.delta());
+    if challenge.mode != Some(ChallengeMode::TimeAttack) || challenge.time_left() > 0. {
+        return;
+    }
+
+    let new_record = challenge.finish(&mut records, &config);
+    announcements.write(Announcement(format!("Time's up, {}", challenge.summary())));
+    *outcome = Outcome::Won {
+        time: TIME_ATTACK_SECONDS,
+        new_record,
+    };
+    next_state.set(GameStates::GameOver);
+}
+
+/// Every board of a run comes from the challenge. A new one once the run is over starts it
+/// again from the first board.
+fn deal_boards(
+    mut new_game: EventReader<NewGame>,
+    game_state: Res<State<GameStates>>,
+    board: Res<BoardSettings>,
+    mut challenge: ResMut<Challenge>,
+    mut board_source: ResMut<BoardSource>,
+) {
+    if new_game.read().count() == 0 {
+        return;
+    }
+    if let (GameStates::GameOver, Some(mode)) = (game_state.get(), challenge.mode) {
+        challenge.start(mode);
+    }
+    *board_source = BoardSource::Dealt(challenge.board(&board));
+}
+
+/// Lives or time left beside the face, and the boards cleared so far.
+fn draw_status(mut cmd: Commands, challenge: Res<Challenge>) {
+    let status = match challenge.mode {
+        Some(ChallengeMode::Survival) => format!("Lives {}", challenge.lives),
+        Some(ChallengeMode::TimeAttack) => format!("{:.1} s left", challenge.time_left()),
+        None => return,
+    };
+
+    for (line, y) in [
+        (status, 320.),
+        (format!("Cleared {}", challenge.cleared), 280.),
+    ] {
+        cmd.spawn((
+            Text2d::new(line),
+            TextFont {
+                font_size: 24.,
+                ..default()
+            },
+            TextColor(STATUS_COLOR),
+            Transform::from_xyz(180., y, 2.),
+            ControlPanelFace,
+        ));
+    }
+}
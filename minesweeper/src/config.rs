@@ -0,0 +1,74 @@
+use std::{fs, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::{Serialize, de::DeserializeOwned};
+
+/// Folder the settings files live in. The platform's config dir unless it's inserted before
+/// `MinesweeperPlugin` is added, as the tests do to keep each game's files to itself.
+#[derive(Resource, Clone)]
+pub struct ConfigDir(pub PathBuf);
+
+impl Default for ConfigDir {
+    fn default() -> Self {
+        let base = std::env::var_os("XDG_CONFIG_HOME")
+            .or_else(|| std::env::var_os("APPDATA"))
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"))
+            })
+            .unwrap_or_default();
+        ConfigDir(base.join("minesweeper"))
+    }
+}
+
+impl ConfigDir {
+    /// Where `load` and `save` keep `name`.
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.0.join(format!("{name}.ron"))
+    }
+
+    /// Reads `<config dir>/<name>.ron`, falling back to the default when it is missing or
+    /// broken.
+    pub fn load<T: DeserializeOwned + Default>(&self, name: &str) -> T {
+        let path = self.path(name);
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return T::default();
+        };
+
+        ron::from_str(&contents).unwrap_or_else(|err| {
+            warn!("Ignoring {}: {err}", path.display());
+            T::default()
+        })
+    }
+
+    /// Writes `value` to `<config dir>/<name>.ron`, a failure only costs the saved settings.
+    pub fn save<T: Serialize>(&self, name: &str, value: &T) {
+        let path = self.path(name);
+        let result = ron::ser::to_string_pretty(value, default())
+            .map_err(|err| err.to_string())
+            .and_then(|contents| {
+                fs::create_dir_all(&self.0)
+                    .and_then(|()| fs::write(&path, contents))
+                    .map_err(|err| err.to_string())
+            });
+
+        if let Err(err) = result {
+            warn!("Couldn't save {}: {err}", path.display());
+        }
+    }
+}
+
+/// The dir `app` keeps its settings in, for plugins loading them as they're built.
+pub fn dir(app: &mut App) -> ConfigDir {
+    app.world_mut().get_resource_or_init::<ConfigDir>().clone()
+}
+
+/// Where the game keeps `name` when nothing else was chosen, for use outside the app.
+pub fn path(name: &str) -> PathBuf {
+    ConfigDir::default().path(name)
+}
+
+/// Reads `name` for a plugin being built.
+pub fn load<T: DeserializeOwned + Default>(app: &mut App, name: &str) -> T {
+    dir(app).load(name)
+}
@@ -0,0 +1,390 @@
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task, block_on, futures_lite::future},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BoardSource, CursorWorldPos, GameStates, MINEFILED_HEIGHT, MINEFILED_WIDTH, NewGame,
+    cell_position, cell_size,
+    config::ConfigDir,
+    find_cell_under_cursor, menu,
+    minefield::{BOARD_SIDES, MineField, Stencil, Variant},
+    solver,
+};
+
+/// Work the solvability check may do before calling the board too big to check. It runs
+/// off the main thread, this just stops a board that was edited again from keeping a
+/// thread busy for long.
+const MAX_ANALYSIS_STEPS: usize = 1 << 30;
+const GUESS_COLOR: Color = Color::srgba(1., 0.8, 0., 0.6);
+const START_COLOR: Color = Color::srgba(0., 0.8, 0., 0.4);
+
+/// A hand-made board, saved to `board.ron` in the config dir.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct BoardFile {
+    max_mines_per_cell: i32,
+    anti_mines: bool,
+    stencil: Stencil,
+    /// Mines in each cell by row, negative for anti-mines.
+    mines: Vec<Vec<i32>>,
+}
+
+impl Default for BoardFile {
+    fn default() -> Self {
+        Self {
+            max_mines_per_cell: 1,
+            anti_mines: false,
+            stencil: Stencil::KING,
+            mines: vec![vec![0; MINEFILED_WIDTH]; MINEFILED_HEIGHT],
+        }
+    }
+}
+
+impl BoardFile {
+    /// A rectangle the editor could have made.
+    fn is_valid(&self) -> bool {
+        let width = self.mines.first().map_or(0, Vec::len);
+        BOARD_SIDES.contains(&width)
+            && BOARD_SIDES.contains(&self.mines.len())
+            && self.mines.iter().all(|row| row.len() == width)
+            && self.stencil.is_valid()
+    }
+}
+
+/// The solvability check, redone on a task after every edit.
+#[derive(Resource, Default)]
+struct Analysis {
+    start: Option<(usize, usize)>,
+    /// `None` while the check runs or when the board was too big to finish it.
+    guesses: Option<Vec<(usize, usize)>>,
+    running: Option<Task<Option<Vec<(usize, usize)>>>>,
+}
+
+#[derive(Component, Clone, Copy)]
+enum EditorAction {
+    Wider,
+    Narrower,
+    Taller,
+    Shorter,
+    NextVariant,
+    Clear,
+    Save,
+    Play,
+    MainMenu,
+}
+
+#[derive(Component)]
+struct EditorStatus;
+
+#[derive(Component)]
+struct GuessHighlight;
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<Analysis>()
+        .add_systems(OnEnter(GameStates::Editor), (load_board, spawn_editor_ui))
+        .add_systems(OnExit(GameStates::Editor), clear_highlights)
+        .add_systems(
+            Update,
+            (
+                edit_mines,
+                editor_actions,
+                analyse,
+                finish_analysis,
+                draw_highlights,
+                update_status,
+            )
+                .chain()
+                .run_if(in_state(GameStates::Editor)),
+        );
+}
+
+/// The editor shows every cell open, so mines and numbers are all in view.
+fn editable(mines: &[Vec<i32>], variant: Variant) -> MineField {
+    let mut minefield = MineField::from_mines(mines, variant);
+    for cell in minefield.cells.iter_mut().flatten() {
+        cell.is_open = true;
+    }
+    minefield
+}
+
+fn load_board(config: Res<ConfigDir>, mut minefield_query: Query<&mut MineField>) {
+    let Ok(mut minefield) = minefield_query.single_mut() else {
+        return;
+    };
+
+    let mut file = config.load::<BoardFile>("board");
+    if !file.is_valid() {
+        warn!("Ignoring the saved board, it isn't a rectangle the editor could make");
+        file = BoardFile::default();
+    }
+    let variant = Variant {
+        max_mines_per_cell: file.max_mines_per_cell.max(1),
+        anti_mines: file.anti_mines,
+        stencil: file.stencil,
+    };
+    *minefield = editable(&file.mines, variant);
+}
+
+fn spawn_editor_ui(mut cmd: Commands) {
+    cmd.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(0.),
+            width: Val::Percent(100.),
+            padding: UiRect::all(Val::Px(4.)),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(4.),
+            ..default()
+        },
+        BackgroundColor(menu::OVERLAY_COLOR),
+        // Lets `edit_mines` tell clicks on the strip from clicks on the board under it
+        Interaction::default(),
+        StateScoped(GameStates::Editor),
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+            Text::default(),
+            TextFont {
+                font_size: 20.,
+                ..default()
+            },
+            EditorStatus,
+        ));
+        parent
+            .spawn(Node {
+                flex_wrap: FlexWrap::Wrap,
+                justify_content: JustifyContent::Center,
+                column_gap: Val::Px(4.),
+                row_gap: Val::Px(4.),
+                ..default()
+            })
+            .with_children(|row| {
+                menu::small_button(row, "W-", EditorAction::Narrower);
+                menu::small_button(row, "W+", EditorAction::Wider);
+                menu::small_button(row, "H-", EditorAction::Shorter);
+                menu::small_button(row, "H+", EditorAction::Taller);
+                menu::small_button(row, "Variant", EditorAction::NextVariant);
+                menu::small_button(row, "Clear", EditorAction::Clear);
+                menu::small_button(row, "Save", EditorAction::Save);
+                menu::small_button(row, "Play", EditorAction::Play);
+                menu::small_button(row, "Menu", EditorAction::MainMenu);
+            });
+    });
+}
+
+/// Left click adds a mine, going through the variant's counts like flags do, right click
+/// empties the cell.
+fn edit_mines(
+    mouse_btn: Res<ButtonInput<MouseButton>>,
+    cursor_world_pos: Res<CursorWorldPos>,
+    ui_interactions: Query<&Interaction>,
+    mut minefield_query: Query<&mut MineField>,
+) {
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+    let Ok(mut minefield) = minefield_query.single_mut() else {
+        return;
+    };
+    let Some((row, col)) = cursor_world_pos
+        .0
+        .and_then(|cursor_world_pos| find_cell_under_cursor(&minefield, cursor_world_pos))
+    else {
+        return;
+    };
+
+    if mouse_btn.just_pressed(MouseButton::Left) {
+        let mines = minefield.variant.next_flag(minefield.cells[row][col].mines);
+        minefield.set_mines(row, col, mines);
+    } else if mouse_btn.just_pressed(MouseButton::Right) {
+        minefield.set_mines(row, col, 0);
+    }
+}
+
+fn editor_actions(
+    buttons: Query<(&Interaction, &EditorAction), Changed<Interaction>>,
+    mut minefield_query: Query<&mut MineField>,
+    mut board_source: ResMut<BoardSource>,
+    mut next_state: ResMut<NextState<GameStates>>,
+    mut new_game: EventWriter<NewGame>,
+    config: Res<ConfigDir>,
+) {
+    let Ok(mut minefield) = minefield_query.single_mut() else {
+        return;
+    };
+
+    for (interaction, action) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let mut mines = minefield.mines();
+        let mut variant = minefield.variant;
+        match *action {
+            EditorAction::Wider if minefield.width < *BOARD_SIDES.end() => {
+                mines.iter_mut().for_each(|row| row.push(0));
+            }
+            EditorAction::Narrower if minefield.width > *BOARD_SIDES.start() => {
+                mines.iter_mut().for_each(|row| {
+                    row.pop();
+                });
+            }
+            EditorAction::Taller if minefield.height < *BOARD_SIDES.end() => {
+                mines.push(vec![0; minefield.width]);
+            }
+            EditorAction::Shorter if minefield.height > *BOARD_SIDES.start() => {
+                mines.pop();
+            }
+            EditorAction::NextVariant => {
+                variant = variant.next();
+                // Counts the new variant can't hold are cut down to what it can
+                let least = if variant.anti_mines {
+                    -variant.max_mines_per_cell
+                } else {
+                    0
+                };
+                for mines in mines.iter_mut().flatten() {
+                    *mines = (*mines).clamp(least, variant.max_mines_per_cell);
+                }
+            }
+            EditorAction::Clear => {
+                mines.iter_mut().flatten().for_each(|mines| *mines = 0);
+            }
+            EditorAction::Save => {
+                config.save(
+                    "board",
+                    &BoardFile {
+                        max_mines_per_cell: variant.max_mines_per_cell,
+                        anti_mines: variant.anti_mines,
+                        stencil: variant.stencil,
+                        mines,
+                    },
+                );
+                continue;
+            }
+            EditorAction::Play => {
+                *board_source = BoardSource::Custom { mines, variant };
+                new_game.write(NewGame);
+                continue;
+            }
+            EditorAction::MainMenu => {
+                next_state.set(GameStates::MainMenu);
+                continue;
+            }
+            _ => continue,
+        }
+        *minefield = editable(&mines, variant);
+    }
+}
+
+fn analyse(
+    minefield_query: Query<&MineField, Changed<MineField>>,
+    mut analysis: ResMut<Analysis>,
+) {
+    let Ok(minefield) = minefield_query.single() else {
+        return;
+    };
+
+    // Playing a big board through takes long enough to hold up the next click, and a task
+    // still running for the last edit is dropped with its answer
+    analysis.start = solver::start_cell(minefield);
+    analysis.guesses = None;
+    analysis.running = analysis.start.map(|start| {
+        let minefield = minefield.clone();
+        AsyncComputeTaskPool::get().spawn(async move {
+            solver::guesses_needed(&minefield, start, MAX_ANALYSIS_STEPS)
+        })
+    });
+    if analysis.running.is_none() {
+        analysis.guesses = Some(Vec::new());
+    }
+}
+
+fn finish_analysis(mut analysis: ResMut<Analysis>) {
+    let Some(task) = &mut analysis.running else {
+        return;
+    };
+    let Some(guesses) = block_on(future::poll_once(task)) else {
+        return;
+    };
+    analysis.running = None;
+    analysis.guesses = guesses;
+}
+
+fn draw_highlights(
+    mut cmd: Commands,
+    minefield_query: Query<&MineField>,
+    analysis: Res<Analysis>,
+    highlights: Query<Entity, With<GuessHighlight>>,
+) {
+    let Ok(minefield) = minefield_query.single() else {
+        return;
+    };
+
+    for entity in highlights {
+        cmd.entity(entity).despawn();
+    }
+
+    let cell_size = cell_size(minefield);
+    let start = analysis.start.map(|start| (start, START_COLOR));
+    let guesses = analysis
+        .guesses
+        .iter()
+        .flatten()
+        .map(|&guess| (guess, GUESS_COLOR));
+    for ((row, col), color) in start.into_iter().chain(guesses) {
+        cmd.spawn((
+            Sprite {
+                color,
+                custom_size: Some(Vec2::splat(cell_size * 0.9)),
+                ..default()
+            },
+            Transform::from_translation(cell_position(minefield, row, col).extend(1.5)),
+            GuessHighlight,
+        ));
+    }
+}
+
+fn clear_highlights(mut cmd: Commands, highlights: Query<Entity, With<GuessHighlight>>) {
+    for entity in highlights {
+        cmd.entity(entity).despawn();
+    }
+}
+
+fn update_status(
+    minefield_query: Query<&MineField>,
+    analysis: Res<Analysis>,
+    mut status: Query<&mut Text, With<EditorStatus>>,
+) {
+    let (Ok(minefield), Ok(mut status)) = (minefield_query.single(), status.single_mut())
+    else {
+        return;
+    };
+
+    let mines: i32 = minefield
+        .cells
+        .iter()
+        .flatten()
+        .map(|cell| cell.mines.abs())
+        .sum();
+    let solvability = match (analysis.start, analysis.guesses.as_ref().map(Vec::len)) {
+        (None, _) => "no safe cell".to_string(),
+        (Some(_), None) if analysis.running.is_some() => "checking for guesses".to_string(),
+        (Some(_), None) => "too big to check for guesses".to_string(),
+        (Some(_), Some(0)) => "solvable without guessing".to_string(),
+        (Some(_), Some(1)) => "1 guess needed".to_string(),
+        (Some(_), Some(guesses)) => format!("{guesses} guesses needed"),
+    };
+    status.0 = format!(
+        "{}x{} {}, {mines} mines, {solvability}",
+        minefield.width,
+        minefield.height,
+        minefield.variant.name(),
+    );
+}
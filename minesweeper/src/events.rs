@@ -0,0 +1,46 @@
+//! What happens on the board, sent by `MinesweeperPlugin` for the app around it to react to.
+
+use bevy::prelude::*;
+
+use crate::Outcome;
+
+/// A hidden cell was clicked open without a mine under it. `opened` holds it and every
+/// cell the click cascaded to.
+#[derive(Event, Clone, Debug)]
+pub struct CellOpened {
+    pub row: usize,
+    pub column: usize,
+    pub opened: Vec<(usize, usize)>,
+}
+
+/// Right clicking a hidden cell cycled its flags to `flags`, negative for anti-mine flags.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct FlagToggled {
+    pub row: usize,
+    pub column: usize,
+    pub flags: i32,
+}
+
+/// An open number with as many flags as mines around it was clicked, opening its other
+/// neighbours. A mine among `opened` ends the game.
+#[derive(Event, Clone, Debug)]
+pub struct ChordPerformed {
+    pub row: usize,
+    pub column: usize,
+    pub opened: Vec<(usize, usize)>,
+}
+
+/// The board was cleared or a mine went off, `time` seconds into the game.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct GameEnded {
+    pub outcome: Outcome,
+    pub time: f32,
+}
+
+/// A mine went off under a click or a chord, or opened by itself when a timed move ran
+/// out. A single game is lost, hot-seat play and challenges go on around it.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct MineHit {
+    pub row: usize,
+    pub column: usize,
+}
@@ -0,0 +1,416 @@
+//! `minesweeper export-gif`: draws a saved replay on the CPU and writes it as an animated
+//! GIF, without a window or a GPU.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::asset::io::file::FileAssetReader;
+
+use crate::{config, replay::Replay};
+
+pub const USAGE: &str = "usage: minesweeper export-gif <output.gif> [--replay <replay.ron>] \
+                         [--frame-time <ms>] [--scale <factor>]";
+/// The last frame stays up this many times longer, so the ending can be seen.
+const LAST_FRAME_HOLD: u32 = 10;
+const PALETTE_SIZE: usize = 256;
+/// GIF's LZW codes never grow past 12 bits.
+const MAX_CODES: u16 = 4096;
+
+struct ExportOptions {
+    output: PathBuf,
+    replay: PathBuf,
+    /// Milliseconds each move stays on screen.
+    frame_time: u32,
+    /// Multiplies the cell images' size.
+    scale: f32,
+}
+
+impl ExportOptions {
+    fn parse(args: &[String]) -> Result<ExportOptions, String> {
+        let mut options = ExportOptions {
+            output: PathBuf::new(),
+            replay: config::path("replay"),
+            frame_time: 200,
+            scale: 0.5,
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| format!("{name} needs a value\n{USAGE}"))
+            };
+            match arg.as_str() {
+                "--replay" => options.replay = PathBuf::from(value(arg)?),
+                "--frame-time" => {
+                    options.frame_time = value(arg)?
+                        .parse()
+                        .map_err(|err| format!("bad --frame-time: {err}"))?;
+                }
+                "--scale" => {
+                    options.scale = value(arg)?
+                        .parse()
+                        .map_err(|err| format!("bad --scale: {err}"))?;
+                }
+                path if options.output.as_os_str().is_empty() => {
+                    options.output = PathBuf::from(path);
+                }
+                other => return Err(format!("unexpected argument {other}\n{USAGE}")),
+            }
+        }
+
+        if options.output.as_os_str().is_empty() {
+            return Err(USAGE.to_string());
+        }
+        if options.scale.is_nan() || options.scale <= 0. {
+            return Err("--scale must be above 0".to_string());
+        }
+        Ok(options)
+    }
+}
+
+/// Runs the command with the arguments that follow `export-gif`.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = ExportOptions::parse(args)?;
+    let replay: Replay = fs::read_to_string(&options.replay)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| ron::from_str(&contents).map_err(|err| err.to_string()))
+        .map_err(|err| format!("Couldn't read {}: {err}", options.replay.display()))?;
+    if replay.frames.is_empty() || replay.mines.is_empty() {
+        return Err(format!("{} holds no moves", options.replay.display()));
+    }
+
+    let tiles = Tiles::load(options.scale)?;
+    let gif = encode(&replay, &tiles, options.frame_time)?;
+    fs::write(&options.output, gif)
+        .map_err(|err| format!("Couldn't write {}: {err}", options.output.display()))?;
+    println!(
+        "Wrote {} frames to {}",
+        replay.frames.len(),
+        options.output.display()
+    );
+    Ok(())
+}
+
+/// The images from `assets/cells`, scaled and turned into palette indices.
+struct Tiles {
+    size: usize,
+    palette: Vec<[u8; 3]>,
+    hidden: Vec<u8>,
+    revealed: Vec<u8>,
+    mine: Vec<u8>,
+    exploded: Vec<u8>,
+    flag: Vec<u8>,
+    numbers: Vec<Vec<u8>>,
+}
+
+impl Tiles {
+    fn load(scale: f32) -> Result<Tiles, String> {
+        let cells_dir = FileAssetReader::get_base_path().join("assets/cells");
+        let read = |name: &str| read_png(&cells_dir.join(name));
+        let hidden = read("unknown.png")?;
+        let revealed = read("empty.png")?;
+        let mine = read("bomb.png")?;
+        let exploded = read("bomb_exploded.png")?;
+        let flag = read("flag.png")?;
+        let numbers = (1..=8)
+            .map(|number| read(&format!("{number}.png")))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let size = ((hidden.width as f32 * scale).round() as usize).max(1);
+        let images: Vec<&RgbImage> = [&hidden, &revealed, &mine, &exploded, &flag]
+            .into_iter()
+            .chain(&numbers)
+            .collect();
+
+        let mut counts = HashMap::new();
+        for image in &images {
+            for &pixel in &image.pixels {
+                *counts.entry(pixel).or_insert(0u32) += 1;
+            }
+        }
+        let palette = median_cut(&counts, PALETTE_SIZE);
+        let mut nearest = HashMap::new();
+        let mut index = |image: &RgbImage| -> Vec<u8> {
+            image
+                .scaled(size)
+                .into_iter()
+                .map(|pixel| {
+                    *nearest
+                        .entry(pixel)
+                        .or_insert_with(|| closest(&palette, pixel))
+                })
+                .collect()
+        };
+
+        Ok(Tiles {
+            size,
+            hidden: index(&hidden),
+            revealed: index(&revealed),
+            mine: index(&mine),
+            exploded: index(&exploded),
+            flag: index(&flag),
+            numbers: numbers.iter().map(&mut index).collect(),
+            palette,
+        })
+    }
+}
+
+struct RgbImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 3]>,
+}
+
+impl RgbImage {
+    /// Nearest neighbour resize to a `size` square.
+    fn scaled(&self, size: usize) -> Vec<[u8; 3]> {
+        (0..size * size)
+            .map(|index| {
+                let (y, x) = (index / size, index % size);
+                self.pixels[y * self.height / size * self.width + x * self.width / size]
+            })
+            .collect()
+    }
+}
+
+fn read_png(path: &Path) -> Result<RgbImage, String> {
+    let image = image::open(path)
+        .map_err(|err| format!("Couldn't load {}: {err}", path.display()))?
+        .into_rgb8();
+    Ok(RgbImage {
+        width: image.width() as usize,
+        height: image.height() as usize,
+        pixels: image.pixels().map(|pixel| pixel.0).collect(),
+    })
+}
+
+/// Splits the colours into `size` boxes, each time halving the box whose widest channel
+/// spans the most, and gives every box's weighted average.
+fn median_cut(counts: &HashMap<[u8; 3], u32>, size: usize) -> Vec<[u8; 3]> {
+    let widest = |colours: &[([u8; 3], u32)]| {
+        (0..3)
+            .map(|channel| {
+                let values = colours.iter().map(|(colour, _)| colour[channel]);
+                let range = values.clone().max().unwrap_or(0) - values.min().unwrap_or(0);
+                (range, channel)
+            })
+            .max()
+            .unwrap_or((0, 0))
+    };
+
+    let mut boxes: Vec<Vec<([u8; 3], u32)>> = vec![
+        counts
+            .iter()
+            .map(|(&colour, &count)| (colour, count))
+            .collect(),
+    ];
+    while boxes.len() < size {
+        let Some((index, (_, channel))) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colours)| colours.len() > 1)
+            .map(|(index, colours)| (index, widest(colours)))
+            .max_by_key(|&(_, (range, _))| range)
+        else {
+            break;
+        };
+
+        let mut colours = boxes.swap_remove(index);
+        colours.sort_by_key(|(colour, _)| colour[channel]);
+        let upper = colours.split_off(colours.len() / 2);
+        boxes.push(colours);
+        boxes.push(upper);
+    }
+
+    boxes
+        .iter()
+        .map(|colours| {
+            let total: u64 = colours.iter().map(|&(_, count)| u64::from(count)).sum();
+            let mut sum = [0u64; 3];
+            for &(colour, count) in colours {
+                for channel in 0..3 {
+                    sum[channel] += u64::from(colour[channel]) * u64::from(count);
+                }
+            }
+            sum.map(|channel| (channel / total.max(1)) as u8)
+        })
+        .collect()
+}
+
+fn closest(palette: &[[u8; 3]], colour: [u8; 3]) -> u8 {
+    let distance = |entry: &[u8; 3]| {
+        (0..3)
+            .map(|channel| (i32::from(entry[channel]) - i32::from(colour[channel])).pow(2))
+            .sum::<i32>()
+    };
+    (0..palette.len())
+        .min_by_key(|&index| distance(&palette[index]))
+        .unwrap_or(0) as u8
+}
+
+/// The tile a cell is drawn with, like `draw_minefield` picks them. Counts without an image
+/// of their own show as an open cell, there's no text on the CPU.
+fn tile_picker<'a>(
+    tiles: &'a Tiles,
+    replay: &Replay,
+    frame: usize,
+) -> impl Fn(usize, usize) -> &'a [u8] {
+    let minefield = replay.minefield(frame);
+    let exploded = replay.exploded.filter(|_| frame + 1 == replay.frames.len());
+    move |row, column| {
+        let cell = &minefield.cells[row][column];
+        match (cell.is_open, cell.is_tagged(), cell.is_mined()) {
+            (false, true, _) => &tiles.flag,
+            (true, _, true) if exploded == Some((row, column)) => &tiles.exploded,
+            (true, _, true) => &tiles.mine,
+            (true, _, false) => match cell.mines_around {
+                n if n > 0 && n as usize <= tiles.numbers.len() => {
+                    &tiles.numbers[n as usize - 1]
+                }
+                _ => &tiles.revealed,
+            },
+            _ => &tiles.hidden,
+        }
+    }
+}
+
+fn encode(replay: &Replay, tiles: &Tiles, frame_time: u32) -> Result<Vec<u8>, String> {
+    let rows = replay.mines.len();
+    let columns = replay.mines[0].len();
+    let (width, height) = (columns * tiles.size, rows * tiles.size);
+    // GIF sizes are 16 bit
+    let (Ok(gif_width), Ok(gif_height)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(format!(
+            "A {width}x{height} GIF is too big, at most {} a side fits, try a smaller --scale",
+            u16::MAX
+        ));
+    };
+
+    let mut gif = Vec::new();
+    gif.extend_from_slice(b"GIF89a");
+    gif.extend_from_slice(&gif_width.to_le_bytes());
+    gif.extend_from_slice(&gif_height.to_le_bytes());
+    // A global colour table of 256 entries, 8 bits per channel
+    gif.extend_from_slice(&[0xF7, 0, 0]);
+    for index in 0..PALETTE_SIZE {
+        gif.extend_from_slice(&tiles.palette.get(index).copied().unwrap_or_default());
+    }
+    // Loop forever
+    gif.extend_from_slice(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00");
+
+    for frame in 0..replay.frames.len() {
+        let pick = tile_picker(tiles, replay, frame);
+        let mut pixels = vec![0u8; width * height];
+        for row in 0..rows {
+            // Row 0 is the bottom of the board
+            let top = (rows - 1 - row) * tiles.size;
+            for column in 0..columns {
+                let tile = pick(row, column);
+                for y in 0..tiles.size {
+                    let start = (top + y) * width + column * tiles.size;
+                    pixels[start..start + tiles.size]
+                        .copy_from_slice(&tile[y * tiles.size..(y + 1) * tiles.size]);
+                }
+            }
+        }
+
+        let hold = if frame + 1 == replay.frames.len() {
+            LAST_FRAME_HOLD
+        } else {
+            1
+        };
+        let delay = (frame_time.saturating_mul(hold) / 10).min(u32::from(u16::MAX)) as u16;
+        gif.extend_from_slice(&[0x21, 0xF9, 0x04, 0x00]);
+        gif.extend_from_slice(&delay.to_le_bytes());
+        gif.extend_from_slice(&[0x00, 0x00]);
+
+        gif.push(0x2C);
+        gif.extend_from_slice(&[0, 0, 0, 0]);
+        gif.extend_from_slice(&gif_width.to_le_bytes());
+        gif.extend_from_slice(&gif_height.to_le_bytes());
+        gif.push(0x00);
+        gif.push(8);
+        for block in lzw(&pixels).chunks(255) {
+            gif.push(block.len() as u8);
+            gif.extend_from_slice(block);
+        }
+        gif.push(0x00);
+    }
+
+    gif.push(0x3B);
+    Ok(gif)
+}
+
+/// GIF flavoured LZW with 8 bit symbols.
+pub(crate) fn lzw(pixels: &[u8]) -> Vec<u8> {
+    const CLEAR: u16 = 256;
+    const END: u16 = 257;
+
+    let mut out = BitWriter::default();
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next_code = END + 1;
+    let mut size = 9;
+    out.write(CLEAR, size);
+
+    let Some((&first, rest)) = pixels.split_first() else {
+        out.write(END, size);
+        return out.finish();
+    };
+    let mut prefix = u16::from(first);
+    for &pixel in rest {
+        if let Some(&code) = table.get(&(prefix, pixel)) {
+            prefix = code;
+            continue;
+        }
+
+        out.write(prefix, size);
+        if next_code == MAX_CODES {
+            out.write(CLEAR, size);
+            table.clear();
+            next_code = END + 1;
+            size = 9;
+        } else {
+            table.insert((prefix, pixel), next_code);
+            // The decoder adds its entries a code late, so the width grows with this one
+            if next_code == 1 << size {
+                size += 1;
+            }
+            next_code += 1;
+        }
+        prefix = u16::from(pixel);
+    }
+    out.write(prefix, size);
+    out.write(END, size);
+    out.finish()
+}
+
+/// Packs codes least significant bit first.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u32) {
+        self.buffer |= u32::from(code) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
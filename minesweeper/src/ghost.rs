@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CellSprite, ControlPanelFace, GameStates, GameTimer, Outcome, PauseState, cell_position,
+    cell_size, classic,
+    config::{self, ConfigDir},
+    draw_control_panel, draw_minefield,
+    minefield::MineField,
+    recording::ReplayLog,
+    replay::{Frame, Replay},
+    skin::CellTextures,
+};
+
+const GHOST_ALPHA: f32 = 0.35;
+const AHEAD_COLOR: Color = Color::srgb(0.2, 0.9, 0.2);
+const BEHIND_COLOR: Color = Color::srgb(1., 0.3, 0.2);
+const HINT_COLOR: Color = Color::srgb(0.6, 0.6, 0.6);
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BestRun {
+    pub time: f32,
+    pub replay: Replay,
+}
+
+impl BestRun {
+    /// Where the run stood `time` seconds in.
+    fn frame_at(&self, time: f32) -> Option<&Frame> {
+        self.replay
+            .frames
+            .iter()
+            .take_while(|frame| frame.time <= time)
+            .last()
+    }
+
+    /// How long the run took to open `opened` cells.
+    fn time_to_open(&self, opened: usize) -> Option<f32> {
+        self.replay
+            .frames
+            .iter()
+            .find(|frame| opened_cells(frame) >= opened)
+            .map(|frame| frame.time)
+    }
+}
+
+/// The fastest win on every board played, by `Replay::layout`. Saved to `ghosts.ron` in
+/// the config dir.
+#[derive(Resource, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct BestRuns(pub HashMap<String, BestRun>);
+
+/// The best run on the board being played, set by `start_new_game`.
+#[derive(Resource, Default)]
+pub struct GhostRace {
+    pub best: Option<BestRun>,
+    /// Whether the board can be dealt again, out of the editor or from a seed picked at
+    /// startup. A random layout only comes back through "Same board", so its runs aren't
+    /// worth saving.
+    pub replayable: bool,
+    /// A win on a board that wasn't replayable, kept until the next board in case that's
+    /// the same one.
+    pub last_win: Option<BestRun>,
+    /// Toggled with G, and kept for the next boards.
+    pub shown: bool,
+}
+
+pub fn plugin(app: &mut App) {
+    let best_runs = config::load::<BestRuns>(app, "ghosts");
+    app.init_resource::<GhostRace>()
+        .insert_resource(best_runs)
+        .add_systems(OnEnter(GameStates::GameOver), keep_best_run.run_if(classic))
+        .add_systems(
+            Update,
+            (
+                toggle_ghost.run_if(in_state(GameStates::Playing)),
+                draw_ghost
+                    .after(draw_minefield)
+                    .run_if(in_state(PauseState::Running)),
+                draw_split
+                    .after(draw_control_panel)
+                    .run_if(in_state(PauseState::Running).or(in_state(GameStates::GameOver))),
+            ),
+        );
+}
+
+fn opened_cells(frame: &Frame) -> usize {
+    frame
+        .rows
+        .iter()
+        .map(|row| row.bytes().filter(|&state| state == b'o').count())
+        .sum()
+}
+
+fn toggle_ghost(keyboard: Res<ButtonInput<KeyCode>>, mut race: ResMut<GhostRace>) {
+    if keyboard.just_pressed(KeyCode::KeyG) {
+        race.shown = !race.shown;
+    }
+}
+
+/// Cells the best run had opened by now, and the player hasn't, show through faintly.
+fn draw_ghost(
+    mut cmd: Commands,
+    race: Res<GhostRace>,
+    timer: Res<GameTimer>,
+    textures: Res<CellTextures>,
+    minefield_query: Query<&MineField>,
+) {
+    let (Some(best), true, Ok(minefield)) = (&race.best, race.shown, minefield_query.single())
+    else {
+        return;
+    };
+    let Some(frame) = best.frame_at(timer.0.elapsed_secs()) else {
+        return;
+    };
+
+    let cell_size = cell_size(minefield);
+    for (row, (cells, states)) in minefield.cells.iter().zip(&frame.rows).enumerate() {
+        for (column, (cell, state)) in cells.iter().zip(states.bytes()).enumerate() {
+            if state != b'o' || cell.is_open {
+                continue;
+            }
+            cmd.spawn((
+                Sprite {
+                    image: textures.revealed.clone(),
+                    color: Color::WHITE.with_alpha(GHOST_ALPHA),
+                    custom_size: Some(Vec2::splat(cell_size * 0.9)),
+                    ..default()
+                },
+                Transform::from_translation(cell_position(minefield, row, column).extend(1.5)),
+                CellSprite,
+            ));
+        }
+    }
+}
+
+/// Seconds behind the best run at the same number of opened cells, negative when ahead.
+/// Until the ghost is shown, a hint that there's one to race.
+fn draw_split(
+    mut cmd: Commands,
+    race: Res<GhostRace>,
+    timer: Res<GameTimer>,
+    minefield_query: Query<&MineField>,
+) {
+    let (Some(best), Ok(minefield)) = (&race.best, minefield_query.single()) else {
+        return;
+    };
+
+    let (text, font_size, color) = if race.shown {
+        let opened = minefield
+            .cells
+            .iter()
+            .flatten()
+            .filter(|cell| cell.is_open && !cell.is_mined())
+            .count();
+        let Some(ghost_time) = best.time_to_open(opened).filter(|_| opened > 0) else {
+            return;
+        };
+        let split = timer.0.elapsed_secs() - ghost_time;
+        let color = if split <= 0. {
+            AHEAD_COLOR
+        } else {
+            BEHIND_COLOR
+        };
+        (format!("{split:+.1}"), 32., color)
+    } else {
+        (format!("G: ghost {:.1}", best.time), 18., HINT_COLOR)
+    };
+
+    cmd.spawn((
+        Text2d::new(text),
+        TextFont {
+            font_size,
+            ..default()
+        },
+        TextColor(color),
+        Transform::from_xyz(180., 300., 2.),
+        ControlPanelFace,
+    ));
+}
+
+/// A win faster than the board's best run becomes its new ghost.
+fn keep_best_run(
+    outcome: Res<Outcome>,
+    log: Res<ReplayLog>,
+    mut race: ResMut<GhostRace>,
+    mut best_runs: ResMut<BestRuns>,
+    config: Res<ConfigDir>,
+) {
+    let Outcome::Won { time, .. } = *outcome else {
+        return;
+    };
+    if !race.replayable {
+        race.last_win = Some(BestRun {
+            time,
+            replay: log.0.clone(),
+        });
+        return;
+    }
+
+    let layout = log.0.layout();
+    if best_runs.0.get(&layout).is_none_or(|best| time < best.time) {
+        best_runs.0.insert(
+            layout,
+            BestRun {
+                time,
+                replay: log.0.clone(),
+            },
+        );
+        config.save("ghosts", &*best_runs);
+    }
+}
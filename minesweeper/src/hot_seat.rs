@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CellSprite, ControlPanelFace, CursorWorldPos, GameStates, NewGame, Outcome, PauseState,
+    accessibility::Announcement,
+    cell_position, cell_size, check_win,
+    config::{self, ConfigDir},
+    draw_control_panel, draw_minefield,
+    events::{CellOpened, ChordPerformed, FlagToggled, MineHit},
+    find_cell_under_cursor,
+    minefield::{MineField, reveal_all_mines},
+    open_cells, place_flags,
+    post_mortem::LossReport,
+    track_cursor,
+};
+
+pub const MAX_PLAYERS: usize = 4;
+const PLAYERS: [(&str, Color); MAX_PLAYERS] = [
+    ("Red", Color::srgb(0.95, 0.25, 0.2)),
+    ("Blue", Color::srgb(0.25, 0.5, 1.)),
+    ("Green", Color::srgb(0.2, 0.8, 0.3)),
+    ("Yellow", Color::srgb(0.95, 0.85, 0.2)),
+];
+const OUT_COLOR: Color = Color::srgb(0.5, 0.5, 0.5);
+/// Per safe cell opened, flood fill and chords included.
+const OPEN_POINTS: i32 = 1;
+/// Per flag on the mines it marks once the game ends, taken away for a wrong one.
+const FLAG_POINTS: i32 = 5;
+/// Taken for setting off a mine, unless mines eliminate.
+const MINE_PENALTY: i32 = 10;
+
+/// Saved to `hotseat.ron` in the config dir.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct HotSeatSettings {
+    pub players: usize,
+    /// Setting off a mine takes a player out of the game rather than costing points.
+    pub eliminate: bool,
+}
+
+impl HotSeatSettings {
+    pub fn change_players(&mut self, delta: i32) {
+        self.players = self
+            .players
+            .saturating_add_signed(delta as isize)
+            .clamp(2, MAX_PLAYERS);
+    }
+}
+
+impl Default for HotSeatSettings {
+    fn default() -> Self {
+        Self {
+            players: 2,
+            eliminate: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Player {
+    /// Points from opening cells and setting off mines, flags only count once it's over.
+    pub score: i32,
+    pub eliminated: bool,
+}
+
+/// Players taking turns on the one board. Without any the game is single player.
+#[derive(Resource, Default)]
+pub struct HotSeat {
+    pub players: Vec<Player>,
+    /// Index of the player whose move it is.
+    pub turn: usize,
+    pub eliminate: bool,
+    /// Who placed the flags on the board, by `(row, column)`.
+    owners: HashMap<(usize, usize), usize>,
+}
+
+impl HotSeat {
+    pub fn start(&mut self, settings: &HotSeatSettings) {
+        *self = HotSeat {
+            players: vec![Player::default(); settings.players],
+            eliminate: settings.eliminate,
+            ..default()
+        };
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.players.is_empty()
+    }
+
+    /// The flag on a cell belongs to someone still playing other than the current player.
+    fn is_guarded(&self, cell: (usize, usize)) -> bool {
+        self.owners
+            .get(&cell)
+            .is_some_and(|&owner| owner != self.turn && !self.players[owner].eliminated)
+    }
+
+    /// Gives the move to the next player still in the game.
+    fn pass_turn(&mut self) {
+        for step in 1..=self.players.len() {
+            let next = (self.turn + step) % self.players.len();
+            if !self.players[next].eliminated {
+                self.turn = next;
+                return;
+            }
+        }
+    }
+
+    /// Scores with every player's flags counted, for the end of the game.
+    pub fn totals(&self, minefield: &MineField) -> Vec<i32> {
+        let mut totals: Vec<i32> = self.players.iter().map(|player| player.score).collect();
+        for (&(row, column), &owner) in &self.owners {
+            let cell = &minefield.cells[row][column];
+            totals[owner] += if cell.is_mined() && cell.flags == cell.mines {
+                FLAG_POINTS
+            } else {
+                -FLAG_POINTS
+            };
+        }
+        totals
+    }
+}
+
+/// Any game not played hot-seat, for systems that only make sense for a single player.
+pub fn solo(hot_seat: Res<HotSeat>) -> bool {
+    !hot_seat.is_active()
+}
+
+pub fn plugin(app: &mut App) {
+    let settings = config::load::<HotSeatSettings>(app, "hotseat");
+    app.init_resource::<HotSeat>()
+        .insert_resource(settings)
+        .add_systems(OnEnter(GameStates::MainMenu), leave_hot_seat)
+        .add_systems(
+            Update,
+            (
+                new_round.run_if(not(solo)),
+                (
+                    guard_flags.after(track_cursor).before(place_flags),
+                    take_turns
+                        .after(open_cells)
+                        .after(place_flags)
+                        .before(check_win),
+                )
+                    .run_if(in_state(PauseState::Running).and(not(solo))),
+                (
+                    draw_flag_owners.after(draw_minefield),
+                    draw_scores.after(draw_control_panel),
+                )
+                    .run_if(
+                        in_state(PauseState::Running)
+                            .or(in_state(GameStates::GameOver))
+                            .and(not(solo)),
+                    ),
+                save_hot_seat_settings.run_if(
+                    resource_changed::<HotSeatSettings>
+                        .and(not(resource_added::<HotSeatSettings>)),
+                ),
+            ),
+        );
+}
+
+/// Back at the main menu, the next game is single player unless hot-seat is picked again.
+fn leave_hot_seat(mut hot_seat: ResMut<HotSeat>) {
+    *hot_seat = HotSeat::default();
+}
+
+/// Every new board starts the same players from nothing.
+fn new_round(mut new_game: EventReader<NewGame>, mut hot_seat: ResMut<HotSeat>) {
+    if new_game.read().count() == 0 {
+        return;
+    }
+    let settings = HotSeatSettings {
+        players: hot_seat.players.len(),
+        eliminate: hot_seat.eliminate,
+    };
+    hot_seat.start(&settings);
+}
+
+/// Right clicks on another player's flag are swallowed before they reach `place_flags`.
+fn guard_flags(
+    hot_seat: Res<HotSeat>,
+    cursor_world_pos: Res<CursorWorldPos>,
+    minefield_query: Query<&MineField>,
+    mut mouse_btn: ResMut<ButtonInput<MouseButton>>,
+) {
+    if !mouse_btn.just_pressed(MouseButton::Right) {
+        return;
+    }
+    let Ok(minefield) = minefield_query.single() else {
+        return;
+    };
+    if let Some(cell) = cursor_world_pos
+        .0
+        .and_then(|cursor_world_pos| find_cell_under_cursor(minefield, cursor_world_pos))
+        && hot_seat.is_guarded(cell)
+    {
+        mouse_btn.clear_just_pressed(MouseButton::Right);
+    }
+}
+
+/// Scores whatever the current player did this frame and hands the board to the next one.
+/// The game ends early once every player has been eliminated.
+fn take_turns(
+    mut hot_seat: ResMut<HotSeat>,
+    mut cells_opened: EventReader<CellOpened>,
+    mut chords: EventReader<ChordPerformed>,
+    mut flags_toggled: EventReader<FlagToggled>,
+    mut mines_hit: EventReader<MineHit>,
+    mut minefield_query: Query<&mut MineField>,
+    mut outcome: ResMut<Outcome>,
+    mut loss_report: ResMut<LossReport>,
+    mut next_state: ResMut<NextState<GameStates>>,
+    mut announcements: EventWriter<Announcement>,
+) {
+    let Ok(mut minefield) = minefield_query.single_mut() else {
+        return;
+    };
+    let turn = hot_seat.turn;
+    let mut moved = false;
+
+    for opened in cells_opened.read() {
+        hot_seat.players[turn].score += opened.opened.len() as i32 * OPEN_POINTS;
+        moved = true;
+    }
+    for chord in chords.read() {
+        let safe = chord
+            .opened
+            .iter()
+            .filter(|&&(row, column)| !minefield.cells[row][column].is_mined())
+            .count();
+        hot_seat.players[turn].score += safe as i32 * OPEN_POINTS;
+        moved = true;
+    }
+    for flag in flags_toggled.read() {
+        if flag.flags == 0 {
+            hot_seat.owners.remove(&(flag.row, flag.column));
+        } else {
+            hot_seat.owners.insert((flag.row, flag.column), turn);
+        }
+        moved = true;
+    }
+    for _ in mines_hit.read() {
+        let (name, _) = PLAYERS[turn];
+        if hot_seat.eliminate {
+            hot_seat.players[turn].eliminated = true;
+            announcements.write(Announcement(format!("{name} is out")));
+        } else {
+            hot_seat.players[turn].score -= MINE_PENALTY;
+            announcements.write(Announcement(format!("{name} loses {MINE_PENALTY} points")));
+        }
+        moved = true;
+    }
+    if !moved {
+        return;
+    }
+
+    if hot_seat.players.iter().all(|player| player.eliminated) {
+        reveal_all_mines(&mut minefield);
+        loss_report.0 = None;
+        *outcome = Outcome::Lost;
+        next_state.set(GameStates::GameOver);
+        return;
+    }
+    hot_seat.pass_turn();
+    announcements.write(Announcement(format!("{}'s turn", PLAYERS[hot_seat.turn].0)));
+}
+
+/// A dot in the corner of each flag, in its owner's colour.
+fn draw_flag_owners(
+    mut cmd: Commands,
+    hot_seat: Res<HotSeat>,
+    minefield_query: Query<&MineField>,
+) {
+    let Ok(minefield) = minefield_query.single() else {
+        return;
+    };
+
+    let cell_size = cell_size(minefield);
+    for (&(row, column), &owner) in &hot_seat.owners {
+        let corner = Vec2::new(-cell_size * 0.3, cell_size * 0.3);
+        cmd.spawn((
+            Sprite {
+                color: PLAYERS[owner].1,
+                custom_size: Some(Vec2::splat(cell_size * 0.2)),
+                ..default()
+            },
+            Transform::from_translation(
+                (cell_position(minefield, row, column) + corner).extend(1.5),
+            ),
+            CellSprite,
+        ));
+    }
+}
+
+/// Scores beside the face while playing, whoever's turn it is marked. Once the game is over
+/// the final scores, flags counted, take the face's place.
+fn draw_scores(
+    mut cmd: Commands,
+    hot_seat: Res<HotSeat>,
+    game_state: Res<State<GameStates>>,
+    minefield_query: Query<&MineField>,
+) {
+    let Ok(minefield) = minefield_query.single() else {
+        return;
+    };
+
+    let game_over = *game_state.get() == GameStates::GameOver;
+    let (scores, x) = if game_over {
+        (hot_seat.totals(minefield), 0.)
+    } else {
+        let scores = hot_seat.players.iter().map(|player| player.score).collect();
+        (scores, 180.)
+    };
+    let best = scores.iter().copied().max().unwrap_or_default();
+
+    for (index, (player, score)) in hot_seat.players.iter().zip(&scores).enumerate() {
+        let (name, color) = PLAYERS[index];
+        let text = match (game_over, index == hot_seat.turn) {
+            (true, _) if *score == best => format!("{name} {score} wins"),
+            (false, true) => format!("> {name} {score}"),
+            _ => format!("{name} {score}"),
+        };
+        let color = if player.eliminated { OUT_COLOR } else { color };
+
+        cmd.spawn((
+            Text2d::new(text),
+            TextFont {
+                font_size: 24.,
+                ..default()
+            },
+            TextColor(color),
+            Transform::from_xyz(x, 345. - index as f32 * 30., 2.),
+            ControlPanelFace,
+        ));
+    }
+}
+
+fn save_hot_seat_settings(settings: Res<HotSeatSettings>, config: Res<ConfigDir>) {
+    config.save("hotseat", &*settings);
+}
@@ -0,0 +1,176 @@
+//! What the game and the leaderboard server send each other, and how the server makes
+//! sure a submitted game was really won.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::minefield::{
+    BOARD_SIDES, MineField, Stencil, Variant, check_win_condition, mine_room, reveal_cells,
+    try_reveal_around_number,
+};
+
+/// Entries kept for each difficulty.
+pub const TOP_ENTRIES: usize = 10;
+
+/// A click on `(row, column)`, seconds into the game. Left clicks open hidden cells and
+/// chord open ones, right clicks cycle flags.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct Move {
+    pub row: usize,
+    pub column: usize,
+    pub flag: bool,
+    pub time: f32,
+}
+
+/// A won game as the player claims it went.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Game {
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    pub mines: usize,
+    pub max_mines_per_cell: i32,
+    pub anti_mines: bool,
+    #[serde(default)]
+    pub stencil: Stencil,
+    /// What `MineField::from_seed` built the board from.
+    pub seed: u64,
+    pub moves: Vec<Move>,
+    /// Seconds on the clock when the board was cleared.
+    pub time: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Submission {
+    pub game: Game,
+    /// Hex of the game's RON, hashed with the key `secret_key` makes of the shared secret.
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Entry {
+    pub name: String,
+    pub time: f32,
+    pub seed: u64,
+}
+
+/// Best entries, fastest first, keyed by `difficulty`.
+pub type Tables = BTreeMap<String, Vec<Entry>>;
+
+/// Boards of the same size, mine count and variant compete with each other.
+pub fn difficulty(width: usize, height: usize, mines: usize, variant: Variant) -> String {
+    format!("{width}x{height}, {mines} mines, {}", variant.name())
+}
+
+/// The key game and server both derive from their shared secret.
+pub fn secret_key(secret: &str) -> [u8; 32] {
+    *blake3::hash(secret.as_bytes()).as_bytes()
+}
+
+fn signature(game: &Game, secret: &str) -> Result<String, String> {
+    let contents = ron::to_string(game).map_err(|err| err.to_string())?;
+    Ok(blake3::keyed_hash(&secret_key(secret), contents.as_bytes())
+        .to_hex()
+        .to_string())
+}
+
+impl Game {
+    pub fn variant(&self) -> Variant {
+        Variant {
+            max_mines_per_cell: self.max_mines_per_cell,
+            anti_mines: self.anti_mines,
+            stencil: self.stencil,
+        }
+    }
+
+    pub fn difficulty(&self) -> String {
+        difficulty(self.width, self.height, self.mines, self.variant())
+    }
+
+    pub fn sign(self, secret: &str) -> Result<Submission, String> {
+        let signature = signature(&self, secret)?;
+        Ok(Submission {
+            game: self,
+            signature,
+        })
+    }
+
+    /// Plays the moves on the board the seed makes, with the same rules as the game: it
+    /// has to end cleared and without a mine going off.
+    pub fn verify(&self) -> Result<(), String> {
+        // The same boards the game lets a player start
+        if !BOARD_SIDES.contains(&self.width) || !BOARD_SIDES.contains(&self.height) {
+            return Err("board size out of range".to_string());
+        }
+        if !(1..=9).contains(&self.max_mines_per_cell) {
+            return Err("mines per cell out of range".to_string());
+        }
+        if !self.stencil.is_valid() {
+            return Err("stencil out of range".to_string());
+        }
+        if self.mines > mine_room(self.width, self.height, self.variant()) {
+            return Err("too many mines for the board".to_string());
+        }
+        if self.moves.is_empty() {
+            return Err("no moves".to_string());
+        }
+        if !self.time.is_finite()
+            || self.time < 0.
+            || self
+                .moves
+                .windows(2)
+                .any(|pair| pair[1].time < pair[0].time)
+            || !self
+                .moves
+                .iter()
+                .all(|step| (0. ..=self.time).contains(&step.time))
+        {
+            return Err("moves out of order".to_string());
+        }
+
+        let variant = self.variant();
+        let mut minefield =
+            MineField::from_seed(self.width, self.height, self.mines, variant, self.seed);
+        for (index, step) in self.moves.iter().enumerate() {
+            let (row, column) = (step.row, step.column);
+            if row >= minefield.height || column >= minefield.width {
+                return Err(format!("move {index} is off the board"));
+            }
+
+            let cell = &mut minefield.cells[row][column];
+            let hit_mine = if step.flag {
+                if !cell.is_open {
+                    cell.flags = variant.next_flag(cell.flags);
+                }
+                false
+            } else if cell.is_open {
+                try_reveal_around_number(&mut minefield, row, column)
+                    .last()
+                    .is_some_and(|&(y, x)| minefield.cells[y][x].is_mined())
+            } else if cell.is_tagged() {
+                false
+            } else if cell.is_mined() {
+                true
+            } else {
+                reveal_cells(&mut minefield, column, row);
+                false
+            };
+            if hit_mine {
+                return Err(format!("move {index} sets off a mine"));
+            }
+        }
+
+        if check_win_condition(&minefield) {
+            Ok(())
+        } else {
+            Err("the board isn't cleared".to_string())
+        }
+    }
+}
+
+impl Submission {
+    pub fn is_signed_with(&self, secret: &str) -> bool {
+        signature(&self.game, secret).is_ok_and(|signature| signature == self.signature)
+    }
+}
@@ -0,0 +1,952 @@
+//! Minesweeper as a Bevy plugin. Add `MinesweeperPlugin` to an app with `DefaultPlugins` and
+//! read the `events` to follow the games being played.
+
+pub mod events;
+pub mod export;
+pub mod leaderboard;
+pub mod minefield;
+pub mod patterns;
+pub mod replay;
+pub mod solver;
+pub mod startup;
+pub mod stream;
+
+mod accessibility;
+mod achievements;
+mod animation;
+mod audio;
+mod challenge;
+mod config;
+mod editor;
+mod ghost;
+mod hot_seat;
+mod menu;
+mod modifiers;
+mod navigation;
+mod neighbourhood;
+mod online;
+mod post_mortem;
+mod recording;
+mod records;
+mod skin;
+mod touch;
+mod training;
+
+#[cfg(test)]
+mod tests;
+
+use accessibility::{AccessibilitySettings, Announcement, describe_cell};
+use animation::{AnimationSettings, CellAnimations, ScreenShake};
+use audio::{AudioSettings, SoundEffect};
+use bevy::{prelude::*, render::camera::ScalingMode, time::Stopwatch, window::PrimaryWindow};
+use challenge::Challenge;
+use config::ConfigDir;
+use events::{CellOpened, ChordPerformed, FlagToggled, GameEnded, MineHit};
+use ghost::{BestRuns, GhostRace};
+use hot_seat::HotSeat;
+use minefield::{
+    MineField, Variant, check_win_condition, reveal_all_mines, reveal_cells,
+    try_reveal_around_number,
+};
+use modifiers::{Fog, Modifiers};
+use online::MoveLog;
+use post_mortem::LossReport;
+use recording::ReplayLog;
+use records::Records;
+use replay::Replay;
+use skin::{BoardBackdrop, CellTextures, ControlPanelTextures, SkinColours};
+use startup::StartScreen;
+
+const MINEFILED_WIDTH: usize = 10;
+const MINEFILED_HEIGHT: usize = 10;
+const BOMBS_AMOUNT: usize = 13;
+const CELL_SIZE: f32 = 50.;
+/// Boards wider or taller than this get smaller cells so they still fit the backdrop.
+const BOARD_SIZE: f32 = 500.;
+/// World area the camera always keeps in view, whatever the window's size or scale factor.
+pub const VIEW_WIDTH: f32 = 600.;
+pub const VIEW_HEIGHT: f32 = 800.;
+
+#[derive(States, Eq, PartialEq, Hash, Debug, Clone, Default)]
+enum GameStates {
+    #[default]
+    MainMenu,
+    Settings,
+    Playing,
+    GameOver,
+    Records,
+    Editor,
+    Training,
+    HotSeat,
+    Modifiers,
+    Achievements,
+    /// Watching a game another process streams, nothing on the board can be clicked.
+    Spectating,
+}
+
+/// Paused hides the board and stops the clock until Escape is pressed again.
+#[derive(SubStates, Eq, PartialEq, Hash, Debug, Clone, Default)]
+#[source(GameStates = GameStates::Playing)]
+enum PauseState {
+    #[default]
+    Running,
+    Paused,
+}
+
+/// How the last game ended, read while in `GameStates::GameOver`.
+#[derive(Resource, Clone, Copy, PartialEq, Debug, Default)]
+pub enum Outcome {
+    #[default]
+    Lost,
+    Won {
+        time: f32,
+        new_record: bool,
+    },
+}
+
+/// Time spent on the current board, only ticks while the game is running.
+#[derive(Resource, Default)]
+struct GameTimer(Stopwatch);
+
+/// Where the cursor points in world space, `None` when it's outside the window.
+#[derive(Resource, Default)]
+struct CursorWorldPos(Option<Vec2>);
+
+/// Where the camera looks, screen shake goes on top. Zooming changes the projection's scale,
+/// which is 1 when the whole view fits the window.
+#[derive(Resource, Default)]
+struct CameraView {
+    pan: Vec2,
+}
+
+/// Seed of the next random board, `None` to pick one at random.
+#[derive(Resource, Default)]
+struct NextSeed(Option<u64>);
+
+/// Throws away the current board and starts a fresh one from `BoardSource`.
+#[derive(Event)]
+struct NewGame;
+
+/// What `NewGame` builds boards from.
+#[derive(Resource, Default)]
+enum BoardSource {
+    /// Random mines with the selected `Variant`, laid out by the game's seed.
+    #[default]
+    Random,
+    /// The same hand-placed mines every time, made in the editor.
+    Custom {
+        mines: Vec<Vec<i32>>,
+        variant: Variant,
+    },
+    /// Random mines with the selected `Variant` on a board a challenge deals.
+    Dealt(BoardSettings),
+}
+
+impl BoardSource {
+    fn minefield(&self, board: &BoardSettings, variant: Variant, seed: u64) -> MineField {
+        match self {
+            BoardSource::Random => {
+                MineField::from_seed(board.width, board.height, board.mines, variant, seed)
+            }
+            BoardSource::Custom { mines, variant } => MineField::from_mines(mines, *variant),
+            BoardSource::Dealt(dealt) => {
+                MineField::from_seed(dealt.width, dealt.height, dealt.mines, variant, seed)
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+struct CellSprite;
+
+#[derive(Component)]
+struct ControlPanel;
+
+/// The face and timer on the control panel, redrawn every frame.
+#[derive(Component)]
+struct ControlPanelFace;
+
+/// Size and mine count of random boards, from `MinesweeperPlugin`.
+#[derive(Resource, Clone, Copy, Debug)]
+struct BoardSettings {
+    width: usize,
+    height: usize,
+    mines: usize,
+}
+
+/// The whole game, menus and saved settings included. Lays itself out for a window of
+/// `VIEW_WIDTH` by `VIEW_HEIGHT`, though any size works.
+pub struct MinesweeperPlugin {
+    /// Columns of random boards.
+    pub width: usize,
+    /// Rows of random boards.
+    pub height: usize,
+    pub mines: usize,
+    /// What's played until the player picks another variant.
+    pub variant: Variant,
+    /// Watch the game streaming on this address instead of playing, see `stream`.
+    pub spectate: Option<String>,
+    /// Deals the first random board from this seed, so it can be played again.
+    pub seed: Option<u64>,
+    /// Name of the skin to start with, over the one picked last time.
+    pub skin: Option<String>,
+    pub screen: StartScreen,
+}
+
+impl Default for MinesweeperPlugin {
+    fn default() -> Self {
+        Self {
+            width: MINEFILED_WIDTH,
+            height: MINEFILED_HEIGHT,
+            mines: BOMBS_AMOUNT,
+            variant: Variant::default(),
+            spectate: None,
+            seed: None,
+            skin: None,
+            screen: StartScreen::default(),
+        }
+    }
+}
+
+impl Plugin for MinesweeperPlugin {
+    fn build(&self, app: &mut App) {
+        let audio_settings = config::load::<AudioSettings>(app, "audio");
+        let records = config::load::<Records>(app, "records");
+        let accessibility_settings =
+            config::load::<AccessibilitySettings>(app, "accessibility");
+        if let Some(address) = &self.spectate {
+            app.insert_resource(stream::SpectateAddress(address.clone()));
+        }
+        app.insert_resource(BoardSettings {
+            width: self.width,
+            height: self.height,
+            mines: self.mines,
+        })
+        .insert_resource(self.variant)
+        .insert_resource(NextSeed(self.seed))
+        .insert_resource(skin::StartSkin(self.skin.clone()))
+        .insert_resource(self.screen)
+        .insert_state(if self.spectate.is_some() {
+            GameStates::Spectating
+        } else {
+            GameStates::MainMenu
+        })
+        .add_sub_state::<PauseState>()
+        .enable_state_scoped_entities::<GameStates>()
+        .enable_state_scoped_entities::<PauseState>()
+        .init_resource::<Outcome>()
+        .init_resource::<BoardSource>()
+        .init_resource::<GameTimer>()
+        .init_resource::<CursorWorldPos>()
+        .init_resource::<CameraView>()
+        .init_resource::<AnimationSettings>()
+        .init_resource::<CellAnimations>()
+        .init_resource::<ScreenShake>()
+        .insert_resource(audio_settings)
+        .insert_resource(records)
+        .insert_resource(accessibility_settings)
+        .add_event::<SoundEffect>()
+        .add_event::<Announcement>()
+        .add_event::<NewGame>()
+        .add_event::<CellOpened>()
+        .add_event::<FlagToggled>()
+        .add_event::<ChordPerformed>()
+        .add_event::<MineHit>()
+        .add_event::<GameEnded>()
+        .add_systems(
+            Startup,
+            (
+                setup,
+                skin::load_skins,
+                audio::setup_sounds,
+                open_start_screen.run_if(not(in_state(GameStates::Spectating))),
+            ),
+        )
+        .add_systems(OnEnter(PauseState::Paused), hide_board)
+        .add_systems(OnEnter(GameStates::GameOver), end_game)
+        .add_systems(
+            Update,
+            (
+                // Without a window, as in the tests, the app sets `CursorWorldPos` itself
+                track_cursor.run_if(any_with_component::<PrimaryWindow>),
+                skip_animations,
+                restart_on_face_click
+                    .run_if(in_state(PauseState::Running).or(in_state(GameStates::GameOver))),
+                (
+                    open_cells,
+                    place_flags,
+                    check_win.run_if(not(challenge::active)),
+                    switch_variant,
+                    tick_timer,
+                )
+                    .chain()
+                    .run_if(in_state(PauseState::Running)),
+                toggle_pause.run_if(in_state(GameStates::Playing)),
+                start_new_game,
+            )
+                .chain(),
+        )
+        .add_systems(
+            Update,
+            (
+                skin::switch_skin,
+                skin::apply_skin
+                    .run_if(resource_changed::<skin::Skins>)
+                    .before(draw_minefield)
+                    .before(draw_control_panel),
+                (draw_minefield, draw_control_panel)
+                    .after(start_new_game)
+                    .run_if(
+                        in_state(PauseState::Running)
+                            .or(in_state(GameStates::GameOver))
+                            .or(in_state(GameStates::Editor))
+                            .or(in_state(GameStates::Training))
+                            .or(in_state(GameStates::Spectating)),
+                    ),
+                animation::toggle_animations,
+                animation::update_particles,
+                animation::shake_camera,
+                audio::audio_controls,
+                audio::save_audio_settings.run_if(
+                    resource_changed::<AudioSettings>
+                        .and(not(resource_added::<AudioSettings>)),
+                ),
+                audio::play_sounds.after(start_new_game),
+                accessibility::accessibility_controls,
+                accessibility::save_accessibility_settings.run_if(
+                    resource_changed::<AccessibilitySettings>
+                        .and(not(resource_added::<AccessibilitySettings>)),
+                ),
+                accessibility::announce.after(start_new_game),
+            ),
+        )
+        .add_plugins((
+            menu::plugin,
+            editor::plugin,
+            post_mortem::plugin,
+            training::plugin,
+            touch::plugin,
+            recording::plugin,
+            online::plugin,
+            ghost::plugin,
+            hot_seat::plugin,
+            challenge::plugin,
+            modifiers::plugin,
+            navigation::plugin,
+            neighbourhood::plugin,
+            achievements::plugin,
+            stream::plugin,
+        ));
+    }
+}
+
+/// The game opens on the main menu, this moves on to the screen asked for at startup.
+fn open_start_screen(
+    screen: Res<StartScreen>,
+    mut next_state: ResMut<NextState<GameStates>>,
+    mut new_game: EventWriter<NewGame>,
+) {
+    match *screen {
+        StartScreen::Menu => {}
+        StartScreen::Play => {
+            new_game.write(NewGame);
+        }
+        StartScreen::Settings => next_state.set(GameStates::Settings),
+        StartScreen::Records => next_state.set(GameStates::Records),
+        StartScreen::Achievements => next_state.set(GameStates::Achievements),
+        StartScreen::Editor => next_state.set(GameStates::Editor),
+        StartScreen::Training => next_state.set(GameStates::Training),
+    }
+}
+
+fn setup(mut cmd: Commands, board: Res<BoardSettings>, variant: Res<Variant>) {
+    cmd.spawn((
+        Camera2d,
+        Projection::Orthographic(OrthographicProjection {
+            scaling_mode: ScalingMode::AutoMin {
+                min_width: VIEW_WIDTH,
+                min_height: VIEW_HEIGHT,
+            },
+            ..OrthographicProjection::default_2d()
+        }),
+    ));
+    cmd.spawn((
+        Sprite {
+            color: Color::srgb(0.5, 0.5, 0.5),
+            custom_size: Some(Vec2::new(550., 550.)),
+            ..default()
+        },
+        Transform::from_xyz(0., -80., 0.),
+        BoardBackdrop,
+        MineField::new(board.width, board.height, board.mines, *variant),
+    ));
+    cmd.spawn((
+        Sprite {
+            color: Color::srgb(0., 0., 0.),
+            custom_size: Some(Vec2::new(550., 150.)),
+            ..default()
+        },
+        Transform::from_xyz(0., 300., 0.),
+        ControlPanel,
+    ));
+}
+
+/// Side of a cell in world units, shrunk when the board wouldn't fit otherwise.
+fn cell_size(minefield: &MineField) -> f32 {
+    CELL_SIZE.min(BOARD_SIZE / minefield.width.max(minefield.height).max(1) as f32)
+}
+
+/// World position of a cell's centre, the board is centred on the backdrop at y = -80.
+fn cell_position(minefield: &MineField, row: usize, column: usize) -> Vec2 {
+    let cell_size = cell_size(minefield);
+    let start_x = -((minefield.width as f32 * cell_size) / 2.0) + cell_size / 2.0;
+    let start_y = -((minefield.height as f32 * cell_size) / 2.0) + cell_size / 2.0 - 80.0;
+
+    Vec2::new(
+        start_x + column as f32 * cell_size,
+        start_y + row as f32 * cell_size,
+    )
+}
+
+fn draw_minefield(
+    mut cmd: Commands,
+    query: Query<&MineField>,
+    textures: Res<CellTextures>,
+    colours: Res<SkinColours>,
+    accessibility: Res<AccessibilitySettings>,
+    animations: Res<CellAnimations>,
+    time: Res<Time>,
+    cell_sprites: Query<Entity, With<CellSprite>>,
+) {
+    let Ok(minefield) = query.single() else {
+        return;
+    };
+
+    for entity in cell_sprites.iter() {
+        cmd.entity(entity).despawn();
+    }
+
+    let now = time.elapsed_secs();
+    let cell_size = cell_size(minefield);
+    let numbers_as_text = accessibility.numbers_as_text();
+    let tint = accessibility.palette.tint(colours.tint);
+    for (row, cells_row) in minefield.cells.iter().enumerate() {
+        for (column, cell) in cells_row.iter().enumerate() {
+            let position = cell_position(minefield, row, column);
+            let is_open = cell.is_open && !animations.is_pending((row, column), now);
+            let scale = match (is_open, cell.is_tagged()) {
+                (true, _) => animations.reveal_scale((row, column), now),
+                (false, true) => animations.flag_scale((row, column), now),
+                _ => 1.,
+            };
+
+            // Counts without a texture of their own get written on top of the cell
+            let number = (is_open && !cell.is_mined() && cell.mines_around != 0)
+                .then_some(cell.mines_around)
+                .filter(|_| numbers_as_text);
+            let (texture, label) = match (is_open, cell.is_tagged(), cell.is_mined()) {
+                _ if number.is_some() => (&textures.revealed, 0),
+                (false, true, _) => (&textures.flag, cell.flags),
+                (true, _, true) if animations.exploded == Some((row, column)) => {
+                    (&textures.exploded, cell.mines)
+                }
+                (true, _, true) => (&textures.mine, cell.mines),
+                (true, _, false) => match cell.mines_around {
+                    0 => (&textures.revealed, 0),
+                    n if n > 0 && n as usize <= textures.numbers.len() => {
+                        (&textures.numbers[n as usize - 1], 0)
+                    }
+                    n => (&textures.revealed, n),
+                },
+                _ => (&textures.hidden, 0),
+            };
+
+            let mut sprite = cmd.spawn((
+                Sprite {
+                    image: texture.clone(),
+                    color: tint,
+                    custom_size: Some(Vec2::splat(cell_size * 0.9)),
+                    ..default()
+                },
+                Transform::from_translation(position.extend(1.))
+                    .with_scale(Vec3::splat(scale)),
+                CellSprite,
+            ));
+
+            if label != 0 && label != 1 {
+                let color = if label < 0 {
+                    colours.anti_label
+                } else {
+                    colours.label
+                };
+                sprite.with_child((
+                    Text2d::new(label.to_string()),
+                    TextFont {
+                        font_size: cell_size * 0.4,
+                        ..default()
+                    },
+                    TextColor(color),
+                    Transform::from_xyz(cell_size * 0.25, -cell_size * 0.25, 1.),
+                ));
+            }
+            if let Some(number) = number {
+                let color = if number < 0 {
+                    colours.anti_label
+                } else {
+                    accessibility.palette.number_colour(number)
+                };
+                sprite.with_child((
+                    Text2d::new(number.to_string()),
+                    TextFont {
+                        font_size: cell_size * 0.7,
+                        ..default()
+                    },
+                    TextColor(color),
+                    Transform::from_xyz(0., 0., 1.),
+                ));
+            }
+        }
+    }
+}
+
+fn draw_control_panel(
+    mut cmd: Commands,
+    game_state: Res<State<GameStates>>,
+    outcome: Res<Outcome>,
+    timer: Res<GameTimer>,
+    textures: Res<ControlPanelTextures>,
+    colours: Res<SkinColours>,
+    hot_seat: Res<HotSeat>,
+    panel_sprites: Query<Entity, With<ControlPanelFace>>,
+) {
+    let texture = match (game_state.get(), *outcome) {
+        (GameStates::GameOver, Outcome::Lost) => &textures.dead,
+        (GameStates::GameOver, Outcome::Won { .. }) => &textures.scared,
+        _ => &textures.casual,
+    };
+
+    for entity in panel_sprites {
+        cmd.entity(entity).despawn();
+    }
+
+    // A finished hot-seat game shows its scoreboard there instead
+    if !(hot_seat.is_active() && *game_state.get() == GameStates::GameOver) {
+        cmd.spawn((
+            Sprite {
+                image: texture.clone(),
+                color: colours.tint,
+                custom_size: Some(Vec2::new(150., 150.)),
+                ..default()
+            },
+            Transform::from_xyz(0., 300., 2.),
+            ControlPanelFace,
+        ));
+    }
+    cmd.spawn((
+        Text2d::new(format!("{:.1}", timer.0.elapsed_secs())),
+        TextFont {
+            font_size: 40.,
+            ..default()
+        },
+        TextColor(Color::srgb(1., 0., 0.)),
+        Transform::from_xyz(-180., 300., 2.),
+        ControlPanelFace,
+    ));
+}
+
+fn hide_board(mut cmd: Commands, cell_sprites: Query<Entity, With<CellSprite>>) {
+    for entity in cell_sprites {
+        cmd.entity(entity).despawn();
+    }
+}
+
+/// A finger on the screen, or one lifted this frame, stands in for the mouse cursor.
+fn track_cursor(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    touches: Res<Touches>,
+    mut cursor_world_pos: ResMut<CursorWorldPos>,
+) {
+    cursor_world_pos.0 = None;
+
+    let Ok(window) = window_query.single() else {
+        return;
+    };
+    let Some(cursor_pos) = touches
+        .iter()
+        .chain(touches.iter_just_released())
+        .map(|touch| touch.position())
+        .next()
+        .or_else(|| window.cursor_position())
+    else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = camera_query.single() else {
+        return;
+    };
+
+    cursor_world_pos.0 = camera
+        .viewport_to_world_2d(camera_transform, cursor_pos)
+        .ok();
+}
+
+/// Any click finishes running reveal animations, so fast players never wait on them.
+fn skip_animations(
+    mouse_btn: Res<ButtonInput<MouseButton>>,
+    time: Res<Time>,
+    mut animations: ResMut<CellAnimations>,
+) {
+    if mouse_btn.get_just_pressed().next().is_some() {
+        animations.skip(time.elapsed_secs());
+    }
+}
+
+fn restart_on_face_click(
+    mouse_btn: Res<ButtonInput<MouseButton>>,
+    cursor_world_pos: Res<CursorWorldPos>,
+    mut new_game: EventWriter<NewGame>,
+) {
+    if mouse_btn.just_pressed(MouseButton::Left)
+        && let Some(cursor_world_pos) = cursor_world_pos.0
+        && cursor_world_pos.distance(Vec2::new(0.0, 300.0)) < 75.0
+    {
+        new_game.write(NewGame);
+    }
+}
+
+fn start_new_game(
+    mut new_game: EventReader<NewGame>,
+    board: Res<BoardSettings>,
+    variant: Res<Variant>,
+    board_source: Res<BoardSource>,
+    mut next_state: ResMut<NextState<GameStates>>,
+    mut minefield_query: Query<&mut MineField>,
+    mut animations: ResMut<CellAnimations>,
+    mut timer: ResMut<GameTimer>,
+    mut replay_log: ResMut<ReplayLog>,
+    mut move_log: ResMut<MoveLog>,
+    mut best_runs: ResMut<BestRuns>,
+    mut race: ResMut<GhostRace>,
+    (hot_seat, modifiers): (Res<HotSeat>, Res<Modifiers>),
+    mut next_seed: ResMut<NextSeed>,
+    mut announcements: EventWriter<Announcement>,
+) {
+    if new_game.read().count() == 0 {
+        return;
+    }
+    let Ok(mut minefield) = minefield_query.single_mut() else {
+        return;
+    };
+
+    let chosen_seed = next_seed.0.take();
+    let seed = chosen_seed.unwrap_or_else(rand::random);
+    *minefield = board_source.minefield(&board, *variant, seed);
+    announcements.write(Announcement(format!(
+        "New {} by {} board, {}",
+        minefield.width,
+        minefield.height,
+        minefield.variant.name()
+    )));
+    animations.clear();
+    timer.0.reset();
+    replay_log.0 = Replay::new(&minefield);
+    race.replayable =
+        matches!(*board_source, BoardSource::Custom { .. }) || chosen_seed.is_some();
+    let layout = replay_log.0.layout();
+    // A win on a random board is held on to in case the same board is asked for next
+    if let Some(last) = race
+        .last_win
+        .take()
+        .filter(|last| race.replayable && last.replay.layout() == layout)
+        && best_runs
+            .0
+            .get(&layout)
+            .is_none_or(|best| last.time < best.time)
+    {
+        best_runs.0.insert(layout.clone(), last);
+    }
+    race.best = best_runs
+        .0
+        .get(&layout)
+        .filter(|_| !hot_seat.is_active() && modifiers.is_empty())
+        .cloned();
+    if let Some(best) = &race.best {
+        announcements.write(Announcement(format!(
+            "Best on this board {:.1} s, G races its ghost",
+            best.time
+        )));
+    }
+    *move_log = MoveLog {
+        seed: matches!(*board_source, BoardSource::Random).then_some(seed),
+        moves: Vec::new(),
+    };
+    next_state.set(GameStates::Playing);
+}
+
+fn open_cells(
+    mut cmd: Commands,
+    mouse_btn: Res<ButtonInput<MouseButton>>,
+    cursor_world_pos: Res<CursorWorldPos>,
+    time: Res<Time>,
+    settings: Res<AnimationSettings>,
+    mut next_state: ResMut<NextState<GameStates>>,
+    mut outcome: ResMut<Outcome>,
+    mut loss_report: ResMut<LossReport>,
+    mut minefield_query: Query<&mut MineField>,
+    mut animations: ResMut<CellAnimations>,
+    mut shake: ResMut<ScreenShake>,
+    mut sounds: EventWriter<SoundEffect>,
+    mut announcements: EventWriter<Announcement>,
+    (hot_seat, challenge, fog): (Res<HotSeat>, Res<Challenge>, Res<Fog>),
+    (mut cells_opened, mut chords, mut mines_hit): (
+        EventWriter<CellOpened>,
+        EventWriter<ChordPerformed>,
+        EventWriter<MineHit>,
+    ),
+) {
+    if !mouse_btn.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Ok(mut minefield) = minefield_query.single_mut() else {
+        return;
+    };
+    let Some((row, col)) = cursor_world_pos
+        .0
+        .and_then(|cursor_world_pos| find_cell_under_cursor(&minefield, cursor_world_pos))
+        .filter(|&(row, col)| !fog.hides(row, col))
+    else {
+        return;
+    };
+
+    let now = time.elapsed_secs();
+    // The board as the player saw it, for the post-mortem if this click loses
+    let before = minefield.clone();
+    let cell = &minefield.cells[row][col];
+
+    let hit_mine = if !cell.is_open && !cell.is_tagged() {
+        if cell.is_mined() {
+            Some((row, col))
+        } else {
+            let opened = reveal_cells(&mut minefield, col, row);
+            sounds.write(if opened.len() > 1 {
+                SoundEffect::Cascade
+            } else {
+                SoundEffect::Open
+            });
+            if settings.enabled {
+                animations.ripple((row, col), &opened, now);
+            }
+            let mut announcement = describe_cell(&minefield, row, col);
+            if opened.len() > 1 {
+                announcement += &format!(", {} cells opened", opened.len());
+            }
+            announcements.write(Announcement(announcement));
+            cells_opened.write(CellOpened {
+                row,
+                column: col,
+                opened,
+            });
+            None
+        }
+    } else if cell.is_open {
+        let opened = try_reveal_around_number(&mut minefield, row, col);
+        if !opened.is_empty() {
+            sounds.write(SoundEffect::Chord);
+            announcements.write(Announcement(format!(
+                "Chord on {}, {} cells opened",
+                describe_cell(&minefield, row, col),
+                opened.len()
+            )));
+        }
+        if settings.enabled {
+            animations.ripple((row, col), &opened, now);
+        }
+        let hit_mine = opened
+            .last()
+            .copied()
+            .filter(|&(y, x)| minefield.cells[y][x].is_mined());
+        if !opened.is_empty() {
+            chords.write(ChordPerformed {
+                row,
+                column: col,
+                opened,
+            });
+        }
+        hit_mine
+    } else {
+        None
+    };
+
+    if let Some((y, x)) = hit_mine {
+        sounds.write(SoundEffect::Explosion);
+        animations.exploded = Some((y, x));
+        if settings.enabled {
+            let position = cell_position(&minefield, y, x);
+            animation::explode(&mut cmd, &mut shake, position);
+        }
+        mines_hit.write(MineHit { row: y, column: x });
+        minefield.cells[y][x].is_open = true;
+        announcements.write(Announcement(format!(
+            "Boom! {}",
+            describe_cell(&minefield, y, x)
+        )));
+        // Other players, or the rest of a challenge, carry on around the mine
+        if hot_seat.is_active() || challenge.is_active() {
+            return;
+        }
+        reveal_all_mines(&mut minefield);
+        let report = solver::post_mortem(&before, (y, x));
+        for line in post_mortem::describe(&report) {
+            announcements.write(Announcement(line));
+        }
+        loss_report.0 = Some(report);
+        *outcome = Outcome::Lost;
+        next_state.set(GameStates::GameOver);
+    }
+}
+
+fn place_flags(
+    mouse_btn: Res<ButtonInput<MouseButton>>,
+    cursor_world_pos: Res<CursorWorldPos>,
+    time: Res<Time>,
+    settings: Res<AnimationSettings>,
+    mut minefield_query: Query<&mut MineField>,
+    mut animations: ResMut<CellAnimations>,
+    mut sounds: EventWriter<SoundEffect>,
+    mut announcements: EventWriter<Announcement>,
+    mut flags_toggled: EventWriter<FlagToggled>,
+    fog: Res<Fog>,
+) {
+    if !mouse_btn.just_pressed(MouseButton::Right) {
+        return;
+    }
+    let Ok(mut minefield) = minefield_query.single_mut() else {
+        return;
+    };
+    let Some((row, col)) = cursor_world_pos
+        .0
+        .and_then(|cursor_world_pos| find_cell_under_cursor(&minefield, cursor_world_pos))
+        .filter(|&(row, col)| !fog.hides(row, col))
+    else {
+        return;
+    };
+
+    let variant = minefield.variant;
+    let cell = &mut minefield.cells[row][col];
+    if !cell.is_open {
+        cell.flags = variant.next_flag(cell.flags);
+        sounds.write(SoundEffect::Flag);
+        if settings.enabled && cell.is_tagged() {
+            animations.flag((row, col), time.elapsed_secs());
+        }
+        flags_toggled.write(FlagToggled {
+            row,
+            column: col,
+            flags: cell.flags,
+        });
+        announcements.write(Announcement(describe_cell(&minefield, row, col)));
+    }
+}
+
+fn check_win(
+    minefield_query: Query<&MineField>,
+    board: Res<BoardSettings>,
+    board_source: Res<BoardSource>,
+    timer: Res<GameTimer>,
+    hot_seat: Res<HotSeat>,
+    modifiers: Res<Modifiers>,
+    mut records: ResMut<Records>,
+    config: Res<ConfigDir>,
+    mut outcome: ResMut<Outcome>,
+    mut next_state: ResMut<NextState<GameStates>>,
+    mut sounds: EventWriter<SoundEffect>,
+    mut announcements: EventWriter<Announcement>,
+) {
+    let Ok(minefield) = minefield_query.single() else {
+        return;
+    };
+    // A mine hit this frame already ended the game
+    if matches!(*next_state, NextState::Pending(GameStates::GameOver)) {
+        return;
+    }
+
+    if check_win_condition(minefield) {
+        let time = timer.0.elapsed_secs();
+        // Records are single player times on random boards under the standard rules
+        let new_record = matches!(*board_source, BoardSource::Random)
+            && !hot_seat.is_active()
+            && modifiers.is_empty()
+            && records.submit(&board, minefield.variant, time, &config);
+        sounds.write(SoundEffect::Win);
+        announcements.write(Announcement(if new_record {
+            format!("Cleared in {time:.1} s, a new record")
+        } else {
+            format!("Cleared in {time:.1} s")
+        }));
+        *outcome = Outcome::Won { time, new_record };
+        next_state.set(GameStates::GameOver);
+    }
+}
+
+/// The clock stopped with the game, so it holds how long the game took.
+fn end_game(outcome: Res<Outcome>, timer: Res<GameTimer>, mut ended: EventWriter<GameEnded>) {
+    ended.write(GameEnded {
+        outcome: *outcome,
+        time: timer.0.elapsed_secs(),
+    });
+}
+
+fn tick_timer(time: Res<Time>, mut timer: ResMut<GameTimer>) {
+    timer.0.tick(time.delta());
+}
+
+fn toggle_pause(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    pause_state: Res<State<PauseState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    if keyboard.just_pressed(KeyCode::Escape) {
+        next_pause_state.set(match pause_state.get() {
+            PauseState::Running => PauseState::Paused,
+            PauseState::Paused => PauseState::Running,
+        });
+    }
+}
+
+fn switch_variant(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut variant: ResMut<Variant>,
+    mut board_source: ResMut<BoardSource>,
+    mut new_game: EventWriter<NewGame>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyV) {
+        *variant = variant.next();
+        *board_source = BoardSource::Random;
+        new_game.write(NewGame);
+    }
+}
+
+/// One player on one board, rule modifiers or not.
+fn single_game(hot_seat: Res<HotSeat>, challenge: Res<Challenge>) -> bool {
+    !hot_seat.is_active() && !challenge.is_active()
+}
+
+/// A single game under the standard rules, the only kind with ghosts and leaderboard entries.
+fn classic(
+    hot_seat: Res<HotSeat>,
+    challenge: Res<Challenge>,
+    modifiers: Res<Modifiers>,
+) -> bool {
+    !hot_seat.is_active() && !challenge.is_active() && modifiers.is_empty()
+}
+
+fn find_cell_under_cursor(minefield: &MineField, cursor_pos: Vec2) -> Option<(usize, usize)> {
+    // Cell (0, 0) is centred on its position, so shift by half a cell to land on its corner
+    let grid_pos = (cursor_pos - cell_position(minefield, 0, 0)) / cell_size(minefield) + 0.5;
+    if grid_pos.x < 0. || grid_pos.y < 0. {
+        return None;
+    }
+
+    let (row, col) = (grid_pos.y as usize, grid_pos.x as usize);
+    (row < minefield.height && col < minefield.width).then_some((row, col))
+}
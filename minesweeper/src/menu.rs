@@ -0,0 +1,718 @@
+use bevy::prelude::*;
+
+use crate::{
+    BoardSettings, BoardSource, GameStates, NewGame, Outcome, PauseState,
+    accessibility::AccessibilitySettings,
+    achievements::{ACHIEVEMENTS, Achievements},
+    animation::AnimationSettings,
+    audio::{AudioSettings, VOLUME_STEP},
+    challenge::{Challenge, ChallengeMode},
+    config::ConfigDir,
+    hot_seat::{HotSeat, HotSeatSettings},
+    minefield::{MineField, Variant},
+    modifiers::{MOVE_LIMIT_STEP, Modifiers},
+    neighbourhood::Stencils,
+    online::{self, LeaderboardSettings, OnlineScores},
+    patterns::Pattern,
+    post_mortem::{self, LossReport},
+    records::Records,
+    skin::Skins,
+    touch::{LONG_PRESS_STEP, TouchSettings},
+    training::TrainingStats,
+};
+
+const BUTTON_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
+const HOVERED_BUTTON_COLOR: Color = Color::srgb(0.35, 0.35, 0.35);
+const PRESSED_BUTTON_COLOR: Color = Color::srgb(0.15, 0.45, 0.15);
+const SCREEN_COLOR: Color = Color::srgb(0.1, 0.1, 0.1);
+pub const OVERLAY_COLOR: Color = Color::srgba(0., 0., 0., 0.7);
+
+/// What a menu button does when pressed.
+#[derive(Component, Clone, Copy)]
+enum MenuAction {
+    Play,
+    /// Plays the same kind of board again, a custom one stays custom.
+    Restart,
+    /// Plays the very board just finished again, to race its ghost.
+    SameBoard,
+    Resume,
+    MainMenu,
+    Settings,
+    Records,
+    Achievements,
+    Editor,
+    Training,
+    Quit,
+    NextVariant,
+    NextNeighbourhood,
+    NextSkin,
+    ToggleAnimations,
+    ToggleMute,
+    ChangeMasterVolume(f32),
+    ChangeEffectsVolume(f32),
+    NextPalette,
+    ToggleTextNumbers,
+    ToggleAnnouncements,
+    ChangeLongPress(f32),
+    HotSeat,
+    StartHotSeat,
+    Challenge(ChallengeMode),
+    ChangePlayers(i32),
+    ToggleElimination,
+    Modifiers,
+    ChangeFog(i32),
+    ChangeMoveLimit(i32),
+    ChangeMoveTime(i32),
+}
+
+/// Text on the settings screen showing a setting's current value.
+#[derive(Component, Clone, Copy)]
+enum SettingValue {
+    Variant,
+    Neighbourhood,
+    Skin,
+    Animations,
+    Mute,
+    MasterVolume,
+    EffectsVolume,
+    Palette,
+    TextNumbers,
+    Announcements,
+    LongPress,
+    Players,
+    Elimination,
+    Fog,
+    MoveLimit,
+    MoveTime,
+}
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameStates::MainMenu), spawn_main_menu)
+        .add_systems(OnEnter(GameStates::Settings), spawn_settings)
+        .add_systems(OnEnter(GameStates::Records), spawn_records)
+        .add_systems(OnEnter(GameStates::Achievements), spawn_achievements)
+        .add_systems(OnEnter(GameStates::HotSeat), spawn_hot_seat)
+        .add_systems(OnEnter(GameStates::Modifiers), spawn_modifiers)
+        .add_systems(OnEnter(GameStates::GameOver), spawn_game_over)
+        .add_systems(OnEnter(PauseState::Paused), spawn_pause)
+        .add_systems(
+            Update,
+            (
+                button_colours,
+                menu_actions,
+                update_setting_values.run_if(
+                    in_state(GameStates::Settings)
+                        .or(in_state(GameStates::HotSeat))
+                        .or(in_state(GameStates::Modifiers)),
+                ),
+            ),
+        );
+}
+
+fn screen(cmd: &mut Commands, state: GameStates) -> Entity {
+    cmd.spawn((
+        Node {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(12.),
+            ..default()
+        },
+        BackgroundColor(SCREEN_COLOR),
+        StateScoped(state),
+    ))
+    .id()
+}
+
+fn title(parent: &mut ChildSpawnerCommands, text: impl Into<String>) {
+    parent.spawn((
+        Text::new(text),
+        TextFont {
+            font_size: 48.,
+            ..default()
+        },
+        Node {
+            margin: UiRect::bottom(Val::Px(24.)),
+            ..default()
+        },
+    ));
+}
+
+fn label(parent: &mut ChildSpawnerCommands, text: impl Into<String>) {
+    parent.spawn((
+        Text::new(text),
+        TextFont {
+            font_size: 24.,
+            ..default()
+        },
+    ));
+}
+
+fn button(parent: &mut ChildSpawnerCommands, text: impl Into<String>, action: MenuAction) {
+    parent
+        .spawn((
+            Button,
+            Node {
+                min_width: Val::Px(200.),
+                padding: UiRect::axes(Val::Px(16.), Val::Px(8.)),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            BackgroundColor(BUTTON_COLOR),
+            action,
+        ))
+        .with_child((
+            Text::new(text),
+            TextFont {
+                font_size: 24.,
+                ..default()
+            },
+        ));
+}
+
+/// A setting's name and value with the buttons changing it next to them.
+fn setting_row(
+    parent: &mut ChildSpawnerCommands,
+    name: &str,
+    value: SettingValue,
+    buttons: &[(&str, MenuAction)],
+) {
+    parent
+        .spawn(Node {
+            width: Val::Px(520.),
+            align_items: AlignItems::Center,
+            column_gap: Val::Px(8.),
+            ..default()
+        })
+        .with_children(|row| {
+            row.spawn((
+                Text::new(name),
+                TextFont {
+                    font_size: 24.,
+                    ..default()
+                },
+                Node {
+                    width: Val::Px(170.),
+                    ..default()
+                },
+            ));
+            row.spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 24.,
+                    ..default()
+                },
+                Node {
+                    flex_grow: 1.,
+                    ..default()
+                },
+                value,
+            ));
+            for &(text, action) in buttons {
+                small_button(row, text, action);
+            }
+        });
+}
+
+/// A button just big enough for its text, `action` is whatever component tells what it does.
+pub fn small_button(parent: &mut ChildSpawnerCommands, text: &str, action: impl Component) {
+    parent
+        .spawn((
+            Button,
+            Node {
+                padding: UiRect::axes(Val::Px(12.), Val::Px(4.)),
+                ..default()
+            },
+            BackgroundColor(BUTTON_COLOR),
+            action,
+        ))
+        .with_child((
+            Text::new(text),
+            TextFont {
+                font_size: 24.,
+                ..default()
+            },
+        ));
+}
+
+fn spawn_main_menu(mut cmd: Commands) {
+    let root = screen(&mut cmd, GameStates::MainMenu);
+    cmd.entity(root).with_children(|parent| {
+        title(parent, "Minesweeper");
+        button(parent, "Play", MenuAction::Play);
+        button(parent, "Hot-seat", MenuAction::HotSeat);
+        for mode in ChallengeMode::ALL {
+            button(parent, mode.name(), MenuAction::Challenge(mode));
+        }
+        button(parent, "Settings", MenuAction::Settings);
+        button(parent, "Modifiers", MenuAction::Modifiers);
+        button(parent, "Records", MenuAction::Records);
+        button(parent, "Achievements", MenuAction::Achievements);
+        button(parent, "Training", MenuAction::Training);
+        button(parent, "Editor", MenuAction::Editor);
+        button(parent, "Quit", MenuAction::Quit);
+    });
+}
+
+fn spawn_settings(mut cmd: Commands) {
+    let root = screen(&mut cmd, GameStates::Settings);
+    cmd.entity(root).with_children(|parent| {
+        title(parent, "Settings");
+        setting_row(
+            parent,
+            "Variant",
+            SettingValue::Variant,
+            &[(">", MenuAction::NextVariant)],
+        );
+        setting_row(
+            parent,
+            "Neighbourhood",
+            SettingValue::Neighbourhood,
+            &[(">", MenuAction::NextNeighbourhood)],
+        );
+        setting_row(
+            parent,
+            "Skin",
+            SettingValue::Skin,
+            &[(">", MenuAction::NextSkin)],
+        );
+        setting_row(
+            parent,
+            "Animations",
+            SettingValue::Animations,
+            &[(">", MenuAction::ToggleAnimations)],
+        );
+        setting_row(
+            parent,
+            "Sound",
+            SettingValue::Mute,
+            &[(">", MenuAction::ToggleMute)],
+        );
+        setting_row(
+            parent,
+            "Master volume",
+            SettingValue::MasterVolume,
+            &[
+                ("-", MenuAction::ChangeMasterVolume(-VOLUME_STEP)),
+                ("+", MenuAction::ChangeMasterVolume(VOLUME_STEP)),
+            ],
+        );
+        setting_row(
+            parent,
+            "Effects volume",
+            SettingValue::EffectsVolume,
+            &[
+                ("-", MenuAction::ChangeEffectsVolume(-VOLUME_STEP)),
+                ("+", MenuAction::ChangeEffectsVolume(VOLUME_STEP)),
+            ],
+        );
+        setting_row(
+            parent,
+            "Palette",
+            SettingValue::Palette,
+            &[(">", MenuAction::NextPalette)],
+        );
+        setting_row(
+            parent,
+            "Text numbers",
+            SettingValue::TextNumbers,
+            &[(">", MenuAction::ToggleTextNumbers)],
+        );
+        setting_row(
+            parent,
+            "Announcements",
+            SettingValue::Announcements,
+            &[(">", MenuAction::ToggleAnnouncements)],
+        );
+        setting_row(
+            parent,
+            "Long press",
+            SettingValue::LongPress,
+            &[
+                ("-", MenuAction::ChangeLongPress(-LONG_PRESS_STEP)),
+                ("+", MenuAction::ChangeLongPress(LONG_PRESS_STEP)),
+            ],
+        );
+        button(parent, "Back", MenuAction::MainMenu);
+    });
+}
+
+/// Players take turns on one board, sharing the mouse.
+fn spawn_hot_seat(mut cmd: Commands) {
+    let root = screen(&mut cmd, GameStates::HotSeat);
+    cmd.entity(root).with_children(|parent| {
+        title(parent, "Hot-seat");
+        setting_row(
+            parent,
+            "Players",
+            SettingValue::Players,
+            &[
+                ("-", MenuAction::ChangePlayers(-1)),
+                ("+", MenuAction::ChangePlayers(1)),
+            ],
+        );
+        setting_row(
+            parent,
+            "Mine hit",
+            SettingValue::Elimination,
+            &[(">", MenuAction::ToggleElimination)],
+        );
+        button(parent, "Start", MenuAction::StartHotSeat);
+        button(parent, "Back", MenuAction::MainMenu);
+    });
+}
+
+/// Rule changes for single games, any of them together.
+fn spawn_modifiers(mut cmd: Commands) {
+    let root = screen(&mut cmd, GameStates::Modifiers);
+    cmd.entity(root).with_children(|parent| {
+        title(parent, "Modifiers");
+        setting_row(
+            parent,
+            "Fog",
+            SettingValue::Fog,
+            &[
+                ("-", MenuAction::ChangeFog(-1)),
+                ("+", MenuAction::ChangeFog(1)),
+            ],
+        );
+        setting_row(
+            parent,
+            "Move limit",
+            SettingValue::MoveLimit,
+            &[
+                ("-", MenuAction::ChangeMoveLimit(-MOVE_LIMIT_STEP)),
+                ("+", MenuAction::ChangeMoveLimit(MOVE_LIMIT_STEP)),
+            ],
+        );
+        setting_row(
+            parent,
+            "Move time",
+            SettingValue::MoveTime,
+            &[
+                ("-", MenuAction::ChangeMoveTime(-1)),
+                ("+", MenuAction::ChangeMoveTime(1)),
+            ],
+        );
+        button(parent, "Back", MenuAction::MainMenu);
+    });
+}
+
+fn spawn_records(
+    mut cmd: Commands,
+    records: Res<Records>,
+    training: Res<TrainingStats>,
+    board: Res<BoardSettings>,
+    variant: Res<Variant>,
+    leaderboard: Res<LeaderboardSettings>,
+) {
+    let root = screen(&mut cmd, GameStates::Records);
+    cmd.entity(root).with_children(|parent| {
+        title(parent, "Records");
+        // The records of the board size and neighbourhood being played
+        label(
+            parent,
+            format!("{} by {}, {} mines", board.width, board.height, board.mines),
+        );
+        for rules in Variant::ALL {
+            let shown = rules.with_stencil(variant.stencil);
+            let time = records
+                .best_time(&board, shown)
+                .map_or("-".to_string(), |time| format!("{time:.1} s"));
+            label(parent, format!("{}: {time}", shown.name()));
+        }
+        title(parent, "Challenges");
+        for mode in ChallengeMode::ALL {
+            let cleared = records
+                .best_challenge(mode)
+                .map_or("-".to_string(), |cleared| format!("{cleared} boards"));
+            label(parent, format!("{}: {cleared}", mode.name()));
+        }
+        title(parent, "Training");
+        for pattern in Pattern::ALL {
+            let form = training.summary(pattern, usize::MAX).map_or(
+                "-".to_string(),
+                |(accuracy, time)| {
+                    format!(
+                        "{:.0}% right, {time:.1} s over {} drills",
+                        accuracy * 100.,
+                        training.drills(pattern),
+                    )
+                },
+            );
+            label(parent, format!("{}: {form}", pattern.name()));
+        }
+        if leaderboard.server.is_some() {
+            title(parent, "Online");
+            label(parent, online::difficulty(&board, *variant));
+            parent.spawn((
+                Text::new("Loading..."),
+                TextFont {
+                    font_size: 18.,
+                    ..default()
+                },
+                OnlineScores,
+            ));
+        }
+        button(parent, "Back", MenuAction::MainMenu);
+    });
+}
+
+fn spawn_achievements(mut cmd: Commands, achievements: Res<Achievements>) {
+    let root = screen(&mut cmd, GameStates::Achievements);
+    cmd.entity(root).with_children(|parent| {
+        title(parent, "Achievements");
+        for achievement in &ACHIEVEMENTS {
+            let mark = if achievements.is_unlocked(achievement) {
+                "[x]"
+            } else {
+                "[ ]"
+            };
+            label(parent, format!("{mark} {}", achievement.name));
+            parent.spawn((
+                Text::new(achievement.description),
+                TextFont {
+                    font_size: 16.,
+                    ..default()
+                },
+            ));
+        }
+        label(parent, format!("Current streak: {}", achievements.streak));
+        button(parent, "Back", MenuAction::MainMenu);
+    });
+}
+
+fn spawn_pause(mut cmd: Commands) {
+    cmd.spawn((
+        Node {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(12.),
+            ..default()
+        },
+        BackgroundColor(OVERLAY_COLOR),
+        StateScoped(PauseState::Paused),
+    ))
+    .with_children(|parent| {
+        title(parent, "Paused");
+        button(parent, "Resume", MenuAction::Resume);
+        button(parent, "Main menu", MenuAction::MainMenu);
+    });
+}
+
+/// A strip along the bottom edge, so the finished board stays in view. Losses get the
+/// post-mortem above the buttons. A challenge sums up the whole run, and can only be played
+/// again from its first board.
+fn spawn_game_over(
+    mut cmd: Commands,
+    outcome: Res<Outcome>,
+    loss_report: Res<LossReport>,
+    challenge: Res<Challenge>,
+) {
+    let text = match *outcome {
+        _ if challenge.is_active() => challenge.summary(),
+        Outcome::Lost => "Boom!".to_string(),
+        Outcome::Won {
+            time,
+            new_record: true,
+        } => format!("New record: {time:.1} s"),
+        Outcome::Won { time, .. } => format!("Cleared in {time:.1} s"),
+    };
+
+    cmd.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(0.),
+            width: Val::Percent(100.),
+            padding: UiRect::all(Val::Px(4.)),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(4.),
+            ..default()
+        },
+        BackgroundColor(OVERLAY_COLOR),
+        StateScoped(GameStates::GameOver),
+    ))
+    .with_children(|parent| {
+        if let (Outcome::Lost, Some(report)) = (*outcome, &loss_report.0) {
+            for line in post_mortem::describe(report) {
+                parent.spawn((
+                    Text::new(line),
+                    TextFont {
+                        font_size: 18.,
+                        ..default()
+                    },
+                ));
+            }
+        }
+        parent
+            .spawn(Node {
+                width: Val::Percent(100.),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::SpaceEvenly,
+                ..default()
+            })
+            .with_children(|row| {
+                label(row, text);
+                button(row, "Play again", MenuAction::Restart);
+                if !challenge.is_active() {
+                    button(row, "Same board", MenuAction::SameBoard);
+                }
+                button(row, "Main menu", MenuAction::MainMenu);
+            });
+    });
+}
+
+fn button_colours(
+    mut buttons: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, mut color) in &mut buttons {
+        color.0 = match interaction {
+            Interaction::Pressed => PRESSED_BUTTON_COLOR,
+            Interaction::Hovered => HOVERED_BUTTON_COLOR,
+            Interaction::None => BUTTON_COLOR,
+        };
+    }
+}
+
+fn menu_actions(
+    buttons: Query<(&Interaction, &MenuAction), Changed<Interaction>>,
+    mut next_state: ResMut<NextState<GameStates>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+    mut new_game: EventWriter<NewGame>,
+    mut exit: EventWriter<AppExit>,
+    mut board_source: ResMut<BoardSource>,
+    mut variant: ResMut<Variant>,
+    (stencils, config): (Res<Stencils>, Res<ConfigDir>),
+    mut skins: ResMut<Skins>,
+    mut animations: ResMut<AnimationSettings>,
+    mut audio: ResMut<AudioSettings>,
+    mut accessibility: ResMut<AccessibilitySettings>,
+    mut touch: ResMut<TouchSettings>,
+    (mut hot_seat_settings, mut modifiers): (ResMut<HotSeatSettings>, ResMut<Modifiers>),
+    (mut hot_seat, mut challenge): (ResMut<HotSeat>, ResMut<Challenge>),
+    minefield_query: Query<&MineField>,
+) {
+    for (interaction, action) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match *action {
+            MenuAction::Play => {
+                *board_source = BoardSource::Random;
+                new_game.write(NewGame);
+            }
+            MenuAction::StartHotSeat => {
+                hot_seat.start(&hot_seat_settings);
+                *board_source = BoardSource::Random;
+                new_game.write(NewGame);
+            }
+            MenuAction::Challenge(mode) => {
+                challenge.start(mode);
+                new_game.write(NewGame);
+            }
+            MenuAction::Restart => {
+                new_game.write(NewGame);
+            }
+            MenuAction::SameBoard => {
+                if let Ok(minefield) = minefield_query.single() {
+                    *board_source = BoardSource::Custom {
+                        mines: minefield.mines(),
+                        variant: minefield.variant,
+                    };
+                    new_game.write(NewGame);
+                }
+            }
+            MenuAction::Editor => next_state.set(GameStates::Editor),
+            MenuAction::Training => next_state.set(GameStates::Training),
+            MenuAction::Resume => next_pause_state.set(PauseState::Running),
+            MenuAction::MainMenu => next_state.set(GameStates::MainMenu),
+            MenuAction::Settings => next_state.set(GameStates::Settings),
+            MenuAction::Records => next_state.set(GameStates::Records),
+            MenuAction::Achievements => next_state.set(GameStates::Achievements),
+            MenuAction::HotSeat => next_state.set(GameStates::HotSeat),
+            MenuAction::Modifiers => next_state.set(GameStates::Modifiers),
+            MenuAction::Quit => {
+                exit.write(AppExit::Success);
+            }
+            MenuAction::ChangePlayers(delta) => hot_seat_settings.change_players(delta),
+            MenuAction::ToggleElimination => {
+                hot_seat_settings.eliminate = !hot_seat_settings.eliminate;
+            }
+            MenuAction::ChangeFog(delta) => modifiers.change_fog(delta),
+            MenuAction::ChangeMoveLimit(delta) => modifiers.change_move_limit(delta),
+            MenuAction::ChangeMoveTime(delta) => modifiers.change_move_time(delta),
+            MenuAction::NextVariant => *variant = variant.next(),
+            MenuAction::NextNeighbourhood => variant.stencil = stencils.next(variant.stencil),
+            MenuAction::NextSkin => skins.select_next(&config),
+            MenuAction::ToggleAnimations => animations.enabled = !animations.enabled,
+            MenuAction::ToggleMute => audio.muted = !audio.muted,
+            MenuAction::ChangeMasterVolume(delta) => audio.change_master(delta),
+            MenuAction::ChangeEffectsVolume(delta) => audio.change_effects(delta),
+            MenuAction::NextPalette => accessibility.palette = accessibility.palette.next(),
+            MenuAction::ToggleTextNumbers => {
+                accessibility.text_numbers = !accessibility.text_numbers;
+            }
+            MenuAction::ToggleAnnouncements => {
+                accessibility.announce = !accessibility.announce;
+            }
+            MenuAction::ChangeLongPress(delta) => touch.change_long_press(delta),
+        }
+    }
+}
+
+fn update_setting_values(
+    mut values: Query<(&mut Text, &SettingValue)>,
+    variant: Res<Variant>,
+    stencils: Res<Stencils>,
+    skins: Res<Skins>,
+    animations: Res<AnimationSettings>,
+    audio: Res<AudioSettings>,
+    accessibility: Res<AccessibilitySettings>,
+    touch: Res<TouchSettings>,
+    hot_seat: Res<HotSeatSettings>,
+    modifiers: Res<Modifiers>,
+) {
+    let on_off = |on: bool| if on { "On" } else { "Off" };
+
+    for (mut text, value) in &mut values {
+        text.0 = match value {
+            SettingValue::Variant => variant.name(),
+            SettingValue::Neighbourhood => stencils.name(variant.stencil),
+            SettingValue::Fog => modifiers
+                .fog
+                .map_or("Off".to_string(), |fog| format!("{fog} cells")),
+            SettingValue::MoveLimit => modifiers
+                .move_limit
+                .map_or("Off".to_string(), |limit| format!("{limit} opens")),
+            SettingValue::MoveTime => modifiers
+                .move_time
+                .map_or("Off".to_string(), |time| format!("{time} s")),
+            SettingValue::Players => hot_seat.players.to_string(),
+            SettingValue::Elimination => if hot_seat.eliminate {
+                "Eliminates"
+            } else {
+                "Costs points"
+            }
+            .to_string(),
+            SettingValue::Skin => skins.current().name.clone(),
+            SettingValue::Animations => on_off(animations.enabled).to_string(),
+            SettingValue::Mute => on_off(!audio.muted).to_string(),
+            SettingValue::MasterVolume => format!("{:.0}%", audio.master * 100.),
+            SettingValue::EffectsVolume => format!("{:.0}%", audio.effects * 100.),
+            SettingValue::Palette => accessibility.palette.name().to_string(),
+            SettingValue::TextNumbers => on_off(accessibility.text_numbers).to_string(),
+            SettingValue::Announcements => on_off(accessibility.announce).to_string(),
+            SettingValue::LongPress => format!("{:.1} s", touch.long_press),
+        };
+    }
+}
@@ -0,0 +1,487 @@
+use std::{collections::VecDeque, ops::RangeInclusive};
+
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+pub const MAX_MINES_PER_CELL: i32 = 3;
+/// Columns or rows a board may have.
+pub const BOARD_SIDES: RangeInclusive<usize> = 2..=200;
+/// Furthest a stencil reaches from its centre, in rows or columns.
+pub const STENCIL_REACH: i32 = 3;
+const STENCIL_SIDE: i32 = 2 * STENCIL_REACH + 1;
+
+/// The cells a number counts mines in, as offsets from it. Flood fill and chording open the
+/// same cells. One bit per cell of a 7x7 square around the centre, row by row.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(transparent)]
+pub struct Stencil(u64);
+
+impl Stencil {
+    /// The 8 surrounding cells of classic minesweeper.
+    pub const KING: Stencil = Stencil::from_rows(&["###", "#.#", "###"]);
+    pub const ORTHOGONAL: Stencil = Stencil::from_rows(&[".#.", "#.#", ".#."]);
+    pub const KNIGHT: Stencil =
+        Stencil::from_rows(&[".#.#.", "#...#", ".....", "#...#", ".#.#."]);
+    /// The 16 cells 2 steps away.
+    pub const RING: Stencil =
+        Stencil::from_rows(&["#####", "#...#", "#...#", "#...#", "#####"]);
+    pub const BUILT_IN: [(&str, Stencil); 4] = [
+        ("King", Stencil::KING),
+        ("Orthogonal", Stencil::ORTHOGONAL),
+        ("Knight", Stencil::KNIGHT),
+        ("Ring", Stencil::RING),
+    ];
+
+    /// Reads a square of `#` for counted cells around the middle one, which never counts.
+    /// Panics on anything `parse` would reject, for the constants above.
+    const fn from_rows(rows: &[&str]) -> Stencil {
+        match Stencil::parse_rows(rows) {
+            Ok(stencil) => stencil,
+            Err(_) => panic!("malformed stencil"),
+        }
+    }
+
+    /// Like `from_rows`, for stencils out of a settings file.
+    pub fn parse(rows: &[String]) -> Result<Stencil, String> {
+        let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+        Stencil::parse_rows(&rows).map_err(str::to_string)
+    }
+
+    const fn parse_rows(rows: &[&str]) -> Result<Stencil, &'static str> {
+        let side = rows.len();
+        if side.is_multiple_of(2) || side > STENCIL_SIDE as usize {
+            return Err("a stencil needs an odd number of rows, at most 7");
+        }
+        let reach = (side / 2) as i32;
+
+        let mut mask = 0;
+        let mut row = 0;
+        while row < side {
+            let cells = rows[row].as_bytes();
+            if cells.len() != side {
+                return Err("a stencil needs as many columns as rows");
+            }
+            let mut column = 0;
+            while column < side {
+                let (dy, dx) = (row as i32 - reach, column as i32 - reach);
+                if cells[column] == b'#' && !(dy == 0 && dx == 0) {
+                    mask |= Stencil::bit(dy, dx);
+                }
+                column += 1;
+            }
+            row += 1;
+        }
+
+        if mask == 0 {
+            Err("a stencil needs at least one cell")
+        } else {
+            Ok(Stencil(mask))
+        }
+    }
+
+    /// Whether the stencil counts at least one cell, and only cells `parse` could have
+    /// given it.
+    pub fn is_valid(self) -> bool {
+        let square = (1 << (STENCIL_SIDE * STENCIL_SIDE)) - 1;
+        self.0 != 0 && self.0 & !square == 0 && self.0 & Stencil::bit(0, 0) == 0
+    }
+
+    const fn bit(dy: i32, dx: i32) -> u64 {
+        1 << ((dy + STENCIL_REACH) * STENCIL_SIDE + dx + STENCIL_REACH) as u32
+    }
+
+    /// `(row, column)` offsets of the counted cells.
+    pub fn offsets(self) -> impl Iterator<Item = (i32, i32)> {
+        (-STENCIL_REACH..=STENCIL_REACH)
+            .flat_map(|dy| (-STENCIL_REACH..=STENCIL_REACH).map(move |dx| (dy, dx)))
+            .filter(move |&(dy, dx)| self.0 & Stencil::bit(dy, dx) != 0)
+    }
+
+    /// The cells that count this one. The same stencil unless it's lopsided.
+    pub fn mirrored(self) -> Stencil {
+        Stencil(
+            self.offsets()
+                .fold(0, |mask, (dy, dx)| mask | Stencil::bit(-dy, -dx)),
+        )
+    }
+
+    pub fn name(self) -> String {
+        Stencil::BUILT_IN
+            .iter()
+            .find(|(_, stencil)| *stencil == self)
+            .map_or_else(
+                || format!("Stencil {:x}", self.0),
+                |(name, _)| name.to_string(),
+            )
+    }
+}
+
+impl Default for Stencil {
+    fn default() -> Self {
+        Stencil::KING
+    }
+}
+
+/// Mine rules a board is generated and played with.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Variant {
+    /// How many mines a single cell can hold, 1 is classic minesweeper.
+    pub max_mines_per_cell: i32,
+    /// Some cells hold anti-mines, which subtract from the neighbour counts.
+    pub anti_mines: bool,
+    /// Which cells count as a cell's neighbours.
+    pub stencil: Stencil,
+}
+
+impl Variant {
+    pub const CLASSIC: Variant = Variant {
+        max_mines_per_cell: 1,
+        anti_mines: false,
+        stencil: Stencil::KING,
+    };
+    pub const ALL: [Variant; 4] = [
+        Variant::CLASSIC,
+        Variant {
+            max_mines_per_cell: MAX_MINES_PER_CELL,
+            anti_mines: false,
+            stencil: Stencil::KING,
+        },
+        Variant {
+            max_mines_per_cell: 1,
+            anti_mines: true,
+            stencil: Stencil::KING,
+        },
+        Variant {
+            max_mines_per_cell: MAX_MINES_PER_CELL,
+            anti_mines: true,
+            stencil: Stencil::KING,
+        },
+    ];
+
+    pub fn name(self) -> String {
+        let rules = match (self.max_mines_per_cell > 1, self.anti_mines) {
+            (false, false) => "Classic",
+            (true, false) => "Multi-mine",
+            (false, true) => "Anti-mine",
+            (true, true) => "Multi-mine + anti-mine",
+        };
+        if self.stencil == Stencil::KING {
+            rules.to_string()
+        } else {
+            format!("{rules}, {}", self.stencil.name())
+        }
+    }
+
+    /// The next mine rules, keeping the stencil.
+    pub fn next(self) -> Variant {
+        let index = Variant::ALL
+            .iter()
+            .position(|v| v.with_stencil(self.stencil) == self)
+            .unwrap_or(0);
+        Variant::ALL[(index + 1) % Variant::ALL.len()].with_stencil(self.stencil)
+    }
+
+    pub fn with_stencil(self, stencil: Stencil) -> Variant {
+        Variant { stencil, ..self }
+    }
+
+    /// Flag count a right click moves to: 1..=N mines, then -1..=-N anti-mines, then back to 0.
+    pub fn next_flag(self, flags: i32) -> i32 {
+        if (0..self.max_mines_per_cell).contains(&flags) {
+            flags + 1
+        } else if flags > 0 && self.anti_mines {
+            -1
+        } else if flags < 0 && -flags < self.max_mines_per_cell {
+            flags - 1
+        } else {
+            0
+        }
+    }
+}
+
+impl Default for Variant {
+    fn default() -> Self {
+        Variant::CLASSIC
+    }
+}
+
+#[derive(Clone)]
+pub struct Cell {
+    /// Mines in the cell, negative for anti-mines.
+    pub mines: i32,
+    pub is_open: bool,
+    /// Flags placed by the player, negative when marking anti-mines.
+    pub flags: i32,
+    /// Sum of the neighbouring `mines`, this is the number shown to the player.
+    pub mines_around: i32,
+    /// Neighbours holding any mine at all. Anti-mines can cancel out `mines_around`, so
+    /// only this tells whether flood fill may continue.
+    pub mined_neighbours: u32,
+}
+
+impl Cell {
+    pub fn new(
+        mines: i32,
+        is_open: bool,
+        flags: i32,
+        mines_around: i32,
+        mined_neighbours: u32,
+    ) -> Cell {
+        Self {
+            mines,
+            is_open,
+            flags,
+            mines_around,
+            mined_neighbours,
+        }
+    }
+
+    pub fn is_mined(&self) -> bool {
+        self.mines != 0
+    }
+
+    pub fn is_tagged(&self) -> bool {
+        self.flags != 0
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct MineField {
+    pub cells: Vec<Vec<Cell>>,
+    pub width: usize,
+    pub height: usize,
+    pub variant: Variant,
+}
+
+impl MineField {
+    pub fn new(width: usize, height: usize, mines: usize, variant: Variant) -> MineField {
+        let mines = generate_mines(&mut rand::rng(), width, height, mines, variant);
+        MineField::from_mines(&mines, variant)
+    }
+
+    /// Always the same board for the same `seed`, on any machine.
+    pub fn from_seed(
+        width: usize,
+        height: usize,
+        mines: usize,
+        variant: Variant,
+        seed: u64,
+    ) -> MineField {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mines = generate_mines(&mut rng, width, height, mines, variant);
+        MineField::from_mines(&mines, variant)
+    }
+
+    /// A board without a single mine.
+    pub fn empty(width: usize, height: usize, variant: Variant) -> MineField {
+        Self {
+            cells: vec![vec![Cell::new(0, false, 0, 0, 0); width]; height],
+            width,
+            height,
+            variant,
+        }
+    }
+
+    /// A hidden board holding `mines[row][column]` mines in each cell.
+    pub fn from_mines(mines: &[Vec<i32>], variant: Variant) -> MineField {
+        let height = mines.len();
+        let width = mines.first().map_or(0, Vec::len);
+        let mut minefield = MineField::empty(width, height, variant);
+
+        for (row, mines_row) in mines.iter().enumerate() {
+            for (column, &mines) in mines_row.iter().enumerate().take(width) {
+                minefield.set_mines(row, column, mines);
+            }
+        }
+        minefield
+    }
+
+    /// The `mines` of every cell, what `from_mines` takes back.
+    pub fn mines(&self) -> Vec<Vec<i32>> {
+        self.cells
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.mines).collect())
+            .collect()
+    }
+
+    /// Changes the mines in `(row, column)`, keeping the counts that include it up to date.
+    pub fn set_mines(&mut self, row: usize, column: usize, mines: i32) {
+        let old = std::mem::replace(&mut self.cells[row][column].mines, mines);
+        let counted_by = self.variant.stencil.mirrored();
+
+        for (y, x) in neighbours(row, column, self.width, self.height, counted_by) {
+            let cell = &mut self.cells[y][x];
+            cell.mines_around += mines - old;
+            cell.mined_neighbours =
+                cell.mined_neighbours + (mines != 0) as u32 - (old != 0) as u32;
+        }
+    }
+
+    /// Coordinates of the cells `(row, column)` counts, by the variant's stencil.
+    pub fn neighbours(
+        &self,
+        row: usize,
+        column: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + use<> {
+        neighbours(row, column, self.width, self.height, self.variant.stencil)
+    }
+}
+
+/// Most mines a board can be asked for and still leave a cell safe to open.
+pub fn mine_room(width: usize, height: usize, variant: Variant) -> usize {
+    (width * height).saturating_sub(1) * variant.max_mines_per_cell.max(0) as usize
+}
+
+fn generate_mines(
+    rng: &mut impl Rng,
+    width: usize,
+    height: usize,
+    mines: usize,
+    variant: Variant,
+) -> Vec<Vec<i32>> {
+    let mut cells = vec![vec![0i32; width]; height];
+    // Past every cell holding all it can there'd be nowhere left to put the rest
+    let mines = mines.min(width * height * variant.max_mines_per_cell.max(0) as usize);
+
+    let mut i = 0;
+    while i < mines {
+        let row = rng.random_range(0..height);
+        let column = rng.random_range(0..width);
+        let sign = if variant.anti_mines && rng.random_ratio(1, 3) {
+            -1
+        } else {
+            1
+        };
+
+        let mines = cells[row][column];
+        if mines == 0 || (mines.signum() == sign && mines.abs() < variant.max_mines_per_cell) {
+            cells[row][column] += sign;
+            i += 1;
+        }
+    }
+
+    cells
+}
+
+fn neighbours(
+    row: usize,
+    column: usize,
+    width: usize,
+    height: usize,
+    stencil: Stencil,
+) -> impl Iterator<Item = (usize, usize)> {
+    stencil.offsets().filter_map(move |(dy, dx)| {
+        let new_row = row as i32 + dy;
+        let new_column = column as i32 + dx;
+
+        if new_row < 0
+            || new_row >= height as i32
+            || new_column < 0
+            || new_column >= width as i32
+        {
+            return None;
+        }
+        Some((new_row as usize, new_column as usize))
+    })
+}
+
+pub fn reveal_all_mines(minefield: &mut MineField) {
+    for row in &mut minefield.cells {
+        for cell in row {
+            if cell.is_mined() {
+                cell.is_open = true;
+            }
+        }
+    }
+}
+
+/// Chording: once the flags around an open number add up to it, every other hidden
+/// neighbour is opened. Returns the newly opened cells as `(row, column)`, ending with the
+/// mine if one got hit.
+pub fn try_reveal_around_number(
+    minefield: &mut MineField,
+    row: usize,
+    col: usize,
+) -> Vec<(usize, usize)> {
+    let mut opened = Vec::new();
+    let cell = &minefield.cells[row][col];
+    // A 0 can hide mines that cancel out, so it's never chorded
+    if !cell.is_open || cell.mines_around == 0 {
+        return opened;
+    }
+
+    // Mines already set off in hot-seat play count as flagged, nothing else can mark them
+    let flagged: i32 = minefield
+        .neighbours(row, col)
+        .map(|(y, x)| match &minefield.cells[y][x] {
+            cell if cell.is_open && cell.is_mined() => cell.mines,
+            cell => cell.flags,
+        })
+        .sum();
+
+    if flagged == cell.mines_around {
+        for (y, x) in minefield.neighbours(row, col) {
+            let cell = &minefield.cells[y][x];
+            if cell.is_open || cell.is_tagged() {
+                continue;
+            }
+
+            if cell.is_mined() {
+                minefield.cells[y][x].is_open = true;
+                opened.push((y, x));
+                break;
+            }
+            opened.extend(reveal_cells(minefield, x, y));
+        }
+    }
+
+    opened
+}
+
+pub fn check_win_condition(minefield: &MineField) -> bool {
+    minefield
+        .cells
+        .iter()
+        .flatten()
+        .all(|cell| cell.is_mined() || cell.is_open)
+}
+
+/// Opens the cell at `(x, y)` and flood fills outward through cells with no mined
+/// neighbours. Returns the newly opened cells as `(row, column)` in the order they were
+/// opened, which is breadth first from the starting cell.
+pub fn reveal_cells(minefield: &mut MineField, x: usize, y: usize) -> Vec<(usize, usize)> {
+    let mut opened = Vec::new();
+    if y >= minefield.height || x >= minefield.width || !open_cell(minefield, y, x) {
+        return opened;
+    }
+
+    // Cells are marked open as they're queued, so `is_open` doubles as the visited set
+    opened.push((y, x));
+    let mut queue = VecDeque::from([(y, x)]);
+
+    while let Some((row, column)) = queue.pop_front() {
+        if minefield.cells[row][column].mined_neighbours != 0 {
+            continue;
+        }
+        for (new_row, new_column) in minefield.neighbours(row, column) {
+            if open_cell(minefield, new_row, new_column) {
+                opened.push((new_row, new_column));
+                queue.push_back((new_row, new_column));
+            }
+        }
+    }
+
+    opened
+}
+
+/// Opens a single hidden, unflagged and safe cell, returning whether it did.
+fn open_cell(minefield: &mut MineField, row: usize, column: usize) -> bool {
+    let cell = &mut minefield.cells[row][column];
+    if cell.is_open || cell.is_tagged() || cell.is_mined() {
+        return false;
+    }
+    cell.is_open = true;
+    true
+}
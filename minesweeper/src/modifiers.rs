@@ -0,0 +1,381 @@
+use std::ops::RangeInclusive;
+
+use bevy::{prelude::*, time::Stopwatch};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CellSprite, ControlPanelFace, GameStates, NewGame, Outcome, PauseState,
+    accessibility::{Announcement, describe_cell},
+    animation::CellAnimations,
+    audio::SoundEffect,
+    cell_position, cell_size,
+    challenge::Challenge,
+    check_win,
+    config::{self, ConfigDir},
+    draw_control_panel, draw_minefield,
+    events::{CellOpened, ChordPerformed, FlagToggled, MineHit},
+    hot_seat::HotSeat,
+    minefield::{MineField, check_win_condition, reveal_all_mines, reveal_cells},
+    open_cells, place_flags,
+    post_mortem::LossReport,
+    single_game,
+};
+
+const FOG_RANGE: RangeInclusive<u32> = 1..=4;
+const MOVE_LIMIT_RANGE: RangeInclusive<u32> = 5..=100;
+pub const MOVE_LIMIT_STEP: i32 = 5;
+const MOVE_TIME_RANGE: RangeInclusive<u32> = 2..=15;
+const FOG_COLOR: Color = Color::srgb(0.12, 0.12, 0.16);
+const STATUS_COLOR: Color = Color::srgb(0.6, 0.8, 1.);
+const HURRY_COLOR: Color = Color::srgb(1., 0.3, 0.2);
+
+/// Rule changes layered on single player games, in any combination. Saved to
+/// `modifiers.ron` in the config dir.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(default)]
+pub struct Modifiers {
+    /// Only cells within this many steps of an open cell can be seen or clicked.
+    pub fog: Option<u32>,
+    /// Cells that may be clicked or chorded open per board, running out before it's cleared
+    /// loses.
+    pub move_limit: Option<u32>,
+    /// Seconds allowed for each move once the first is made, after which a random hidden
+    /// cell opens by itself, mine or not.
+    pub move_time: Option<u32>,
+}
+
+impl Modifiers {
+    /// The standard rules, the only ones records and ghosts are kept for.
+    pub fn is_empty(&self) -> bool {
+        *self == Modifiers::default()
+    }
+
+    pub fn change_fog(&mut self, delta: i32) {
+        self.fog = step(self.fog, delta, FOG_RANGE);
+    }
+
+    pub fn change_move_limit(&mut self, delta: i32) {
+        self.move_limit = step(self.move_limit, delta, MOVE_LIMIT_RANGE);
+    }
+
+    pub fn change_move_time(&mut self, delta: i32) {
+        self.move_time = step(self.move_time, delta, MOVE_TIME_RANGE);
+    }
+}
+
+/// Stepping up from off starts at the range's start, stepping below it turns the modifier off.
+fn step(value: Option<u32>, delta: i32, range: RangeInclusive<u32>) -> Option<u32> {
+    match value {
+        None if delta > 0 => Some(*range.start()),
+        None => None,
+        Some(value) => {
+            let next = value as i32 + delta;
+            (next >= *range.start() as i32).then(|| (next as u32).min(*range.end()))
+        }
+    }
+}
+
+/// Any modifier is on.
+pub fn modified(modifiers: Res<Modifiers>) -> bool {
+    !modifiers.is_empty()
+}
+
+/// How the current board stands against the modifiers, reset by every new board.
+#[derive(Resource, Default)]
+struct ModifierState {
+    opens: u32,
+    /// Time since the last move, `None` until the first one.
+    move_clock: Option<Stopwatch>,
+}
+
+/// The cells the fog leaves in sight, rebuilt only when the board or the fog changes.
+#[derive(Resource, Default)]
+pub struct Fog {
+    /// Empty while there's no fog.
+    in_sight: Vec<Vec<bool>>,
+    /// Whether anything is open yet. Until then the whole board is covered but the first
+    /// click can go anywhere.
+    started: bool,
+}
+
+impl Fog {
+    /// Covered cells can't be opened or flagged, there's nothing to see on them.
+    pub fn hides(&self, row: usize, column: usize) -> bool {
+        self.started
+            && self
+                .in_sight
+                .get(row)
+                .and_then(|cells| cells.get(column))
+                .is_some_and(|in_sight| !in_sight)
+    }
+}
+
+pub fn plugin(app: &mut App) {
+    let modifiers = config::load::<Modifiers>(app, "modifiers");
+    app.init_resource::<ModifierState>()
+        .init_resource::<Fog>()
+        .insert_resource(modifiers)
+        .add_systems(
+            Update,
+            (
+                reset_modifiers,
+                (
+                    (count_moves, time_moves)
+                        .chain()
+                        .after(open_cells)
+                        .after(place_flags)
+                        .before(check_win),
+                    enforce_move_limit.after(check_win),
+                )
+                    .run_if(in_state(PauseState::Running)),
+                draw_fog
+                    .after(draw_minefield)
+                    .after(update_fog)
+                    .run_if(in_state(PauseState::Running)),
+                draw_status
+                    .after(draw_control_panel)
+                    .run_if(in_state(PauseState::Running)),
+            )
+                .run_if(single_game.and(modified)),
+        )
+        .add_systems(
+            Update,
+            (
+                update_fog
+                    .after(open_cells)
+                    .after(place_flags)
+                    .after(time_moves),
+                save_modifiers.run_if(
+                    resource_changed::<Modifiers>.and(not(resource_added::<Modifiers>)),
+                ),
+            ),
+        );
+}
+
+fn reset_modifiers(mut new_game: EventReader<NewGame>, mut state: ResMut<ModifierState>) {
+    if new_game.read().count() != 0 {
+        *state = ModifierState::default();
+    }
+}
+
+/// Any click that changed the board restarts the move clock, opens count against the limit.
+fn count_moves(
+    mut cells_opened: EventReader<CellOpened>,
+    mut chords: EventReader<ChordPerformed>,
+    mut flags_toggled: EventReader<FlagToggled>,
+    mut state: ResMut<ModifierState>,
+) {
+    let opens = cells_opened.read().count() + chords.read().count();
+    let flags = flags_toggled.read().count();
+
+    state.opens += opens as u32;
+    if opens + flags > 0 {
+        state.move_clock = Some(Stopwatch::new());
+    }
+}
+
+/// A move that takes too long opens a random hidden cell, which loses if it's a mine.
+fn time_moves(
+    time: Res<Time>,
+    modifiers: Res<Modifiers>,
+    mut state: ResMut<ModifierState>,
+    mut minefield_query: Query<&mut MineField>,
+    mut animations: ResMut<CellAnimations>,
+    mut outcome: ResMut<Outcome>,
+    mut loss_report: ResMut<LossReport>,
+    mut next_state: ResMut<NextState<GameStates>>,
+    mut mines_hit: EventWriter<MineHit>,
+    mut sounds: EventWriter<SoundEffect>,
+    mut announcements: EventWriter<Announcement>,
+) {
+    let (Some(move_time), Some(clock)) = (modifiers.move_time, &mut state.move_clock) else {
+        return;
+    };
+    if clock.tick(time.delta()).elapsed_secs() < move_time as f32 {
+        return;
+    }
+    clock.reset();
+    let Ok(mut minefield) = minefield_query.single_mut() else {
+        return;
+    };
+
+    let hidden: Vec<(usize, usize)> = (0..minefield.height)
+        .flat_map(|row| (0..minefield.width).map(move |column| (row, column)))
+        .filter(|&(row, column)| {
+            let cell = &minefield.cells[row][column];
+            !cell.is_open && !cell.is_tagged()
+        })
+        .collect();
+    if hidden.is_empty() {
+        return;
+    }
+    let (row, column) = hidden[rand::random_range(0..hidden.len())];
+
+    if minefield.cells[row][column].is_mined() {
+        sounds.write(SoundEffect::Explosion);
+        mines_hit.write(MineHit { row, column });
+        animations.exploded = Some((row, column));
+        reveal_all_mines(&mut minefield);
+        announcements.write(Announcement(format!(
+            "Too slow, {}",
+            describe_cell(&minefield, row, column)
+        )));
+        loss_report.0 = None;
+        *outcome = Outcome::Lost;
+        next_state.set(GameStates::GameOver);
+    } else {
+        reveal_cells(&mut minefield, column, row);
+        sounds.write(SoundEffect::Open);
+        announcements.write(Announcement(format!(
+            "Too slow, opened {}",
+            describe_cell(&minefield, row, column)
+        )));
+    }
+}
+
+/// The board is lost once the opens run out and it still isn't cleared.
+fn enforce_move_limit(
+    modifiers: Res<Modifiers>,
+    state: Res<ModifierState>,
+    mut minefield_query: Query<&mut MineField>,
+    mut outcome: ResMut<Outcome>,
+    mut loss_report: ResMut<LossReport>,
+    mut next_state: ResMut<NextState<GameStates>>,
+    mut announcements: EventWriter<Announcement>,
+) {
+    let Ok(mut minefield) = minefield_query.single_mut() else {
+        return;
+    };
+    if modifiers.move_limit.is_none_or(|limit| state.opens < limit)
+        || matches!(*next_state, NextState::Pending(GameStates::GameOver))
+        || check_win_condition(&minefield)
+    {
+        return;
+    }
+
+    reveal_all_mines(&mut minefield);
+    announcements.write(Announcement("Out of moves".to_string()));
+    loss_report.0 = None;
+    *outcome = Outcome::Lost;
+    next_state.set(GameStates::GameOver);
+}
+
+/// Cells in sight are those within the fog's reach of an open one, found by spreading the
+/// open cells along the rows and then down the columns.
+fn update_fog(
+    modifiers: Res<Modifiers>,
+    hot_seat: Res<HotSeat>,
+    challenge: Res<Challenge>,
+    minefield_query: Query<Ref<MineField>>,
+    mut fog: ResMut<Fog>,
+) {
+    let Ok(minefield) = minefield_query.single() else {
+        return;
+    };
+    if !(minefield.is_changed()
+        || modifiers.is_changed()
+        || hot_seat.is_changed()
+        || challenge.is_changed())
+    {
+        return;
+    }
+    // Fog only covers single games, like the other modifiers
+    let reach = match modifiers.fog {
+        Some(reach) if !hot_seat.is_active() && !challenge.is_active() => reach as usize,
+        _ => {
+            *fog = Fog::default();
+            return;
+        }
+    };
+
+    let across: Vec<Vec<bool>> = minefield
+        .cells
+        .iter()
+        .map(|cells| spread(cells.iter().map(|cell| cell.is_open), reach))
+        .collect();
+    let down: Vec<Vec<bool>> = (0..minefield.width)
+        .map(|column| spread(across.iter().map(|cells| cells[column]), reach))
+        .collect();
+    fog.in_sight = (0..minefield.height)
+        .map(|row| {
+            (0..minefield.width)
+                .map(|column| down[column][row])
+                .collect()
+        })
+        .collect();
+    fog.started = across.iter().flatten().any(|&in_sight| in_sight);
+}
+
+/// Marks every place along `line` within `reach` of a marked one.
+fn spread(line: impl Iterator<Item = bool>, reach: usize) -> Vec<bool> {
+    // Marks before each place, so any stretch of the line is counted by a subtraction
+    let mut marked_before = vec![0];
+    for marked in line {
+        marked_before.push(marked_before[marked_before.len() - 1] + usize::from(marked));
+    }
+    let len = marked_before.len() - 1;
+    (0..len)
+        .map(|at| {
+            marked_before[(at + reach + 1).min(len)] > marked_before[at.saturating_sub(reach)]
+        })
+        .collect()
+}
+
+/// Cells out of sight of every open cell are covered, flags and all. Until something is
+/// open the whole board is.
+fn draw_fog(mut cmd: Commands, fog: Res<Fog>, minefield_query: Query<&MineField>) {
+    let Ok(minefield) = minefield_query.single() else {
+        return;
+    };
+
+    let cell_size = cell_size(minefield);
+    for (row, cells) in fog.in_sight.iter().enumerate() {
+        for (column, &in_sight) in cells.iter().enumerate() {
+            if in_sight {
+                continue;
+            }
+            cmd.spawn((
+                Sprite {
+                    color: FOG_COLOR,
+                    custom_size: Some(Vec2::splat(cell_size * 0.9)),
+                    ..default()
+                },
+                Transform::from_translation(cell_position(minefield, row, column).extend(1.6)),
+                CellSprite,
+            ));
+        }
+    }
+}
+
+/// Opens left and time left for the move beside the face.
+fn draw_status(mut cmd: Commands, modifiers: Res<Modifiers>, state: Res<ModifierState>) {
+    let mut lines = Vec::new();
+    if let Some(limit) = modifiers.move_limit {
+        lines.push((
+            format!("Opens {}", limit.saturating_sub(state.opens)),
+            STATUS_COLOR,
+        ));
+    }
+    if let (Some(move_time), Some(clock)) = (modifiers.move_time, &state.move_clock) {
+        let left = (move_time as f32 - clock.elapsed_secs()).max(0.);
+        let color = if left < 2. { HURRY_COLOR } else { STATUS_COLOR };
+        lines.push((format!("Move {left:.1} s"), color));
+    }
+
+    for (index, (line, color)) in lines.into_iter().enumerate() {
+        cmd.spawn((
+            Text2d::new(line),
+            TextFont {
+                font_size: 24.,
+                ..default()
+            },
+            TextColor(color),
+            Transform::from_xyz(180., 320. - index as f32 * 40., 2.),
+            ControlPanelFace,
+        ));
+    }
+}
+
+fn save_modifiers(modifiers: Res<Modifiers>, config: Res<ConfigDir>) {
+    config.save("modifiers", &*modifiers);
+}
@@ -0,0 +1,427 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    image::ImageSampler,
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    window::PrimaryWindow,
+};
+
+use crate::{
+    CELL_SIZE, CameraView, CursorWorldPos, GameStates, PauseState, VIEW_HEIGHT, VIEW_WIDTH,
+    accessibility::{Announcement, describe_cell},
+    cell_position, cell_size,
+    minefield::MineField,
+};
+
+/// Deepest zoom on boards whose cells are full size, 4 times closer than the whole view.
+const MIN_SCALE: f32 = 0.25;
+/// Scale change per line the mouse wheel turns.
+const ZOOM_STEP: f32 = 0.9;
+/// Scale a frontier jump zooms to when the whole view is showing.
+const JUMP_SCALE: f32 = 0.5;
+/// Screen pixels per second the view pans at, with the arrow keys or the cursor at an edge.
+const PAN_SPEED: f32 = 600.;
+/// How close to the window's edge in pixels the cursor starts panning.
+const EDGE_MARGIN: f32 = 16.;
+/// How quickly a jump or the zoom closes in on its goal, per second.
+const SMOOTHING: f32 = 8.;
+/// Longest side of the minimap in pixels.
+const MINIMAP_SIZE: f32 = 160.;
+const HIDDEN_COLOR: [u8; 4] = [70, 70, 70, 255];
+const OPEN_COLOR: [u8; 4] = [190, 190, 190, 255];
+const FLAG_COLOR: [u8; 4] = [255, 120, 0, 255];
+const MINE_COLOR: [u8; 4] = [220, 30, 30, 255];
+
+/// Where a frontier jump is gliding the view to, dropped once the player pans.
+#[derive(Resource, Default)]
+struct PanGoal(Option<Vec2>);
+
+/// The scale the mouse wheel is gliding the camera to, and the point kept in place while it
+/// does.
+#[derive(Resource, Default)]
+struct ZoomGoal(Option<(f32, Vec2)>);
+
+#[derive(Resource)]
+struct Minimap(Handle<Image>);
+
+#[derive(Component)]
+struct MinimapNode;
+
+/// The outline on the minimap of what the camera shows.
+#[derive(Component)]
+struct MinimapViewport;
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<PanGoal>()
+        .init_resource::<ZoomGoal>()
+        .add_systems(Startup, spawn_minimap)
+        .add_systems(OnEnter(GameStates::MainMenu), reset_view)
+        .add_systems(
+            Update,
+            (
+                (zoom, pan, jump_to_frontier, follow_goal, follow_zoom).chain(),
+                update_minimap.after(follow_zoom),
+            )
+                .run_if(
+                    in_state(PauseState::Running)
+                        .or(in_state(GameStates::GameOver))
+                        .or(in_state(GameStates::Editor)),
+                ),
+        )
+        .add_systems(OnExit(GameStates::Playing), hide_minimap)
+        .add_systems(OnExit(GameStates::GameOver), hide_minimap)
+        .add_systems(OnExit(GameStates::Editor), hide_minimap)
+        .add_systems(OnEnter(PauseState::Paused), hide_minimap);
+}
+
+/// Deepest a camera can zoom on `minefield`, far enough to show its cells at full size.
+pub fn min_scale(minefield: &MineField) -> f32 {
+    (cell_size(minefield) / CELL_SIZE).min(MIN_SCALE)
+}
+
+/// Keeps a camera zoomed to `scale` from looking past the edges of the view.
+pub fn clamp_pan(pan: Vec2, scale: f32) -> Vec2 {
+    let reach = Vec2::new(VIEW_WIDTH, VIEW_HEIGHT) / 2. * (1. - scale);
+    pan.clamp(-reach, reach)
+}
+
+fn camera_scale(projection: &Projection) -> f32 {
+    match projection {
+        Projection::Orthographic(projection) => projection.scale,
+        _ => 1.,
+    }
+}
+
+fn reset_view(
+    mut view: ResMut<CameraView>,
+    mut goal: ResMut<PanGoal>,
+    mut zoom_goal: ResMut<ZoomGoal>,
+    mut projection_query: Query<&mut Projection>,
+) {
+    view.pan = Vec2::ZERO;
+    goal.0 = None;
+    zoom_goal.0 = None;
+    if let Ok(mut projection) = projection_query.single_mut()
+        && let Projection::Orthographic(projection) = &mut *projection
+    {
+        projection.scale = 1.;
+    }
+}
+
+/// The mouse wheel zooms, gliding there with the point under the cursor kept in place. Home
+/// zooms all the way back out.
+fn zoom(
+    mut wheel: EventReader<MouseWheel>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    cursor_world_pos: Res<CursorWorldPos>,
+    minefield_query: Query<&MineField>,
+    mut view: ResMut<CameraView>,
+    mut goal: ResMut<PanGoal>,
+    mut zoom_goal: ResMut<ZoomGoal>,
+    mut projection_query: Query<&mut Projection>,
+) {
+    let lines: f32 = wheel
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / 100.,
+        })
+        .sum();
+    let (Ok(minefield), Ok(mut projection)) =
+        (minefield_query.single(), projection_query.single_mut())
+    else {
+        return;
+    };
+    let Projection::Orthographic(projection) = &mut *projection else {
+        return;
+    };
+
+    if keyboard.just_pressed(KeyCode::Home) {
+        projection.scale = 1.;
+        view.pan = Vec2::ZERO;
+        goal.0 = None;
+        zoom_goal.0 = None;
+        return;
+    }
+    if lines == 0. {
+        return;
+    }
+
+    // Notches turned while still gliding add up
+    let from = zoom_goal.0.map_or(projection.scale, |(scale, _)| scale);
+    let scale = (from * ZOOM_STEP.powf(lines)).clamp(min_scale(minefield), 1.);
+    let anchor = cursor_world_pos.0.unwrap_or(view.pan);
+    zoom_goal.0 = Some((scale, anchor));
+}
+
+/// The arrow keys, or the cursor resting at an edge of the window, pan a zoomed in view.
+fn pan(
+    time: Res<Time>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    projection_query: Query<&Projection>,
+    mut view: ResMut<CameraView>,
+    mut goal: ResMut<PanGoal>,
+) {
+    let Ok(projection) = projection_query.single() else {
+        return;
+    };
+    let scale = camera_scale(projection);
+    if scale >= 1. {
+        return;
+    }
+
+    let mut direction = Vec2::ZERO;
+    for (key, step) in [
+        (KeyCode::ArrowLeft, Vec2::NEG_X),
+        (KeyCode::ArrowRight, Vec2::X),
+        (KeyCode::ArrowDown, Vec2::NEG_Y),
+        (KeyCode::ArrowUp, Vec2::Y),
+    ] {
+        if keyboard.pressed(key) {
+            direction += step;
+        }
+    }
+    if let Ok(window) = window_query.single()
+        && window.focused
+        && let Some(cursor) = window.cursor_position()
+    {
+        let size = window.size();
+        // Window coordinates grow downwards
+        direction.x +=
+            f32::from(cursor.x > size.x - EDGE_MARGIN) - f32::from(cursor.x < EDGE_MARGIN);
+        direction.y +=
+            f32::from(cursor.y < EDGE_MARGIN) - f32::from(cursor.y > size.y - EDGE_MARGIN);
+    }
+    if direction == Vec2::ZERO {
+        return;
+    }
+
+    goal.0 = None;
+    let step = direction.normalize() * PAN_SPEED * scale * time.delta_secs();
+    view.pan = clamp_pan(view.pan + step, scale);
+}
+
+/// F glides the view to the hidden cell nearest its middle that borders an open number, the
+/// places still to be worked out.
+fn jump_to_frontier(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    minefield_query: Query<&MineField>,
+    view: Res<CameraView>,
+    mut goal: ResMut<PanGoal>,
+    mut zoom_goal: ResMut<ZoomGoal>,
+    mut projection_query: Query<&mut Projection>,
+    mut announcements: EventWriter<Announcement>,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyF) {
+        return;
+    }
+    let (Ok(minefield), Ok(mut projection)) =
+        (minefield_query.single(), projection_query.single_mut())
+    else {
+        return;
+    };
+    let Projection::Orthographic(projection) = &mut *projection else {
+        return;
+    };
+
+    let nearest = (0..minefield.height)
+        .flat_map(|row| (0..minefield.width).map(move |column| (row, column)))
+        .filter(|&(row, column)| {
+            let cell = &minefield.cells[row][column];
+            cell.is_open && !cell.is_mined() && cell.mined_neighbours != 0
+        })
+        .flat_map(|(row, column)| minefield.neighbours(row, column))
+        .filter(|&(row, column)| {
+            let cell = &minefield.cells[row][column];
+            !cell.is_open && !cell.is_tagged()
+        })
+        .min_by(|&(a_row, a_column), &(b_row, b_column)| {
+            let a = cell_position(minefield, a_row, a_column).distance_squared(view.pan);
+            let b = cell_position(minefield, b_row, b_column).distance_squared(view.pan);
+            a.total_cmp(&b)
+        });
+    let Some((row, column)) = nearest else {
+        announcements.write(Announcement("No frontier to work on".to_string()));
+        return;
+    };
+
+    if projection.scale >= 1. {
+        projection.scale = JUMP_SCALE.max(min_scale(minefield));
+        zoom_goal.0 = None;
+    }
+    goal.0 = Some(clamp_pan(
+        cell_position(minefield, row, column),
+        projection.scale,
+    ));
+    announcements.write(Announcement(format!(
+        "Frontier at {}",
+        describe_cell(minefield, row, column)
+    )));
+}
+
+fn follow_goal(time: Res<Time>, mut view: ResMut<CameraView>, mut goal: ResMut<PanGoal>) {
+    let Some(target) = goal.0 else {
+        return;
+    };
+
+    view.pan = view
+        .pan
+        .lerp(target, 1. - (-SMOOTHING * time.delta_secs()).exp());
+    if view.pan.distance(target) < 0.5 {
+        view.pan = target;
+        goal.0 = None;
+    }
+}
+
+fn follow_zoom(
+    time: Res<Time>,
+    mut view: ResMut<CameraView>,
+    mut goal: ResMut<ZoomGoal>,
+    mut projection_query: Query<&mut Projection>,
+) {
+    let Some((target, anchor)) = goal.0 else {
+        return;
+    };
+    let Ok(mut projection) = projection_query.single_mut() else {
+        return;
+    };
+    let Projection::Orthographic(projection) = &mut *projection else {
+        return;
+    };
+
+    let mut scale = projection
+        .scale
+        .lerp(target, 1. - (-SMOOTHING * time.delta_secs()).exp());
+    if (scale - target).abs() < 0.001 {
+        scale = target;
+        goal.0 = None;
+    }
+    view.pan = clamp_pan(
+        anchor + (view.pan - anchor) * (scale / projection.scale),
+        scale,
+    );
+    projection.scale = scale;
+}
+
+fn spawn_minimap(mut cmd: Commands, mut images: ResMut<Assets<Image>>) {
+    let image = images.add(minimap_image(1, 1, HIDDEN_COLOR.to_vec()));
+    cmd.spawn((
+        ImageNode::new(image.clone()),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.),
+            right: Val::Px(8.),
+            ..default()
+        },
+        Visibility::Hidden,
+        MinimapNode,
+    ))
+    .with_child((
+        Node {
+            position_type: PositionType::Absolute,
+            border: UiRect::all(Val::Px(1.)),
+            ..default()
+        },
+        BorderColor(Color::WHITE),
+        MinimapViewport,
+    ));
+    cmd.insert_resource(Minimap(image));
+}
+
+fn minimap_image(width: usize, height: usize, data: Vec<u8>) -> Image {
+    let mut image = Image::new(
+        Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+    image
+}
+
+/// While zoomed in, a pixel per cell in the corner shows what's explored, flagged and still
+/// hidden, with the part of the board in view outlined.
+fn update_minimap(
+    minimap: Res<Minimap>,
+    view: Res<CameraView>,
+    minefield_query: Query<Ref<MineField>>,
+    projection_query: Query<&Projection>,
+    mut images: ResMut<Assets<Image>>,
+    mut map_query: Query<(&mut Node, &mut Visibility), With<MinimapNode>>,
+    mut viewport_query: Query<&mut Node, (With<MinimapViewport>, Without<MinimapNode>)>,
+) {
+    let (Ok(minefield), Ok(projection), Ok((mut map, mut visibility))) = (
+        minefield_query.single(),
+        projection_query.single(),
+        map_query.single_mut(),
+    ) else {
+        return;
+    };
+    if minefield.width == 0 || minefield.height == 0 {
+        return;
+    }
+    if minefield.is_changed()
+        && let Some(image) = images.get_mut(&minimap.0)
+    {
+        // Image rows run top down, board rows bottom up
+        let data = minefield
+            .cells
+            .iter()
+            .rev()
+            .flatten()
+            .flat_map(
+                |cell| match (cell.is_open, cell.is_mined(), cell.is_tagged()) {
+                    (true, true, _) => MINE_COLOR,
+                    (true, false, _) => OPEN_COLOR,
+                    (false, _, true) => FLAG_COLOR,
+                    (false, _, false) => HIDDEN_COLOR,
+                },
+            )
+            .collect();
+        *image = minimap_image(minefield.width, minefield.height, data);
+    }
+
+    let scale = camera_scale(projection);
+    if scale >= 1. {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    }
+    visibility.set_if_neq(Visibility::Inherited);
+
+    let pixel = MINIMAP_SIZE / minefield.width.max(minefield.height).max(1) as f32;
+    let size = Vec2::new(minefield.width as f32, minefield.height as f32) * pixel;
+    map.width = Val::Px(size.x);
+    map.height = Val::Px(size.y);
+
+    // The view's corners in cells from the board's top left, then in minimap pixels
+    let Ok(mut viewport) = viewport_query.single_mut() else {
+        return;
+    };
+    let cell_size = cell_size(&minefield);
+    let top_left = cell_position(&minefield, minefield.height - 1, 0)
+        + Vec2::new(-cell_size, cell_size) / 2.;
+    let half_view = Vec2::new(VIEW_WIDTH, VIEW_HEIGHT) / 2. * scale;
+    let corner = |point: Vec2| {
+        let cells = Vec2::new(point.x - top_left.x, top_left.y - point.y) / cell_size;
+        (cells * pixel).clamp(Vec2::ZERO, size)
+    };
+    let start = corner(view.pan + Vec2::new(-half_view.x, half_view.y));
+    let end = corner(view.pan + Vec2::new(half_view.x, -half_view.y));
+    viewport.left = Val::Px(start.x);
+    viewport.top = Val::Px(start.y);
+    viewport.width = Val::Px(end.x - start.x);
+    viewport.height = Val::Px(end.y - start.y);
+}
+
+fn hide_minimap(mut map_query: Query<&mut Visibility, With<MinimapNode>>) {
+    for mut visibility in &mut map_query {
+        *visibility = Visibility::Hidden;
+    }
+}
@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CursorWorldPos, GameStates, PauseState, cell_position, cell_size,
+    config::{self, ConfigDir},
+    find_cell_under_cursor,
+    minefield::{MineField, Stencil},
+};
+
+const PREVIEW_COLOR: Color = Color::srgb(0.3, 0.7, 1.);
+
+/// A neighbourhood of the player's own: a square of rows, `#` for the cells the middle
+/// one counts, like `["..#..", ".....", "#...#", ".....", "..#.."]`.
+#[derive(Serialize, Deserialize)]
+struct CustomStencil {
+    name: String,
+    rows: Vec<String>,
+}
+
+/// Read from `stencils.ron` in the config dir, never written by the game.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct StencilFile {
+    stencils: Vec<CustomStencil>,
+}
+
+/// Neighbourhoods the settings cycle through, the built-in ones first.
+#[derive(Resource)]
+pub struct Stencils(Vec<(String, Stencil)>);
+
+impl Stencils {
+    fn load(config: &ConfigDir) -> Stencils {
+        let mut stencils: Vec<(String, Stencil)> = Stencil::BUILT_IN
+            .iter()
+            .map(|(name, stencil)| (name.to_string(), *stencil))
+            .collect();
+
+        for custom in config.load::<StencilFile>("stencils").stencils {
+            match Stencil::parse(&custom.rows) {
+                Ok(stencil) if stencils.iter().all(|(_, known)| *known != stencil) => {
+                    stencils.push((custom.name, stencil));
+                }
+                Ok(_) => {}
+                Err(err) => warn!("Ignoring stencil {}: {err}", custom.name),
+            }
+        }
+        Stencils(stencils)
+    }
+
+    pub fn name(&self, stencil: Stencil) -> String {
+        self.0
+            .iter()
+            .find(|(_, known)| *known == stencil)
+            .map_or_else(|| stencil.name(), |(name, _)| name.clone())
+    }
+
+    pub fn next(&self, stencil: Stencil) -> Stencil {
+        let index = self.0.iter().position(|(_, known)| *known == stencil);
+        index.map_or(Stencil::KING, |index| self.0[(index + 1) % self.0.len()].1)
+    }
+}
+
+pub fn plugin(app: &mut App) {
+    let stencils = Stencils::load(&config::dir(app));
+    app.insert_resource(stencils).add_systems(
+        Update,
+        preview_stencil.run_if(in_state(PauseState::Running).or(in_state(GameStates::Editor))),
+    );
+}
+
+/// Outlines the cells a hovered number counts, unless they're the usual 8 around it.
+fn preview_stencil(
+    mut gizmos: Gizmos,
+    cursor_world_pos: Res<CursorWorldPos>,
+    minefield_query: Query<&MineField>,
+) {
+    let Ok(minefield) = minefield_query.single() else {
+        return;
+    };
+    if minefield.variant.stencil == Stencil::KING {
+        return;
+    }
+    let Some((row, column)) = cursor_world_pos
+        .0
+        .and_then(|cursor_world_pos| find_cell_under_cursor(minefield, cursor_world_pos))
+    else {
+        return;
+    };
+    let cell = &minefield.cells[row][column];
+    if !cell.is_open || cell.is_mined() || cell.mined_neighbours == 0 {
+        return;
+    }
+
+    let size = Vec2::splat(cell_size(minefield) * 0.9);
+    for (y, x) in minefield.neighbours(row, column) {
+        gizmos.rect_2d(cell_position(minefield, y, x), size, PREVIEW_COLOR);
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use bevy::{
+    prelude::*,
+    tasks::{IoTaskPool, Task, block_on, futures_lite::future},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BoardSettings, CursorWorldPos, GameStates, GameTimer, Outcome, PauseState,
+    accessibility::Announcement,
+    classic, config, find_cell_under_cursor,
+    leaderboard::{self, Game, Move, Tables},
+    minefield::{MineField, Variant},
+    open_cells, track_cursor,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Saved to `leaderboard.ron` in the config dir, the game stays offline until `server`
+/// is set.
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct LeaderboardSettings {
+    /// Like `http://127.0.0.1:7878`.
+    pub server: Option<String>,
+    pub name: String,
+    /// Shared with the server, which turns away games not signed with it.
+    pub secret: String,
+}
+
+impl Default for LeaderboardSettings {
+    fn default() -> Self {
+        Self {
+            server: None,
+            name: "Player".to_string(),
+            secret: String::new(),
+        }
+    }
+}
+
+/// Every click of the game being played, restarted by `start_new_game`. Only random
+/// boards have a seed, and only those can be submitted.
+#[derive(Resource, Default)]
+pub struct MoveLog {
+    pub seed: Option<u64>,
+    pub moves: Vec<Move>,
+}
+
+/// The online table on the records screen, filled in once the server answers.
+#[derive(Component)]
+pub struct OnlineScores;
+
+#[derive(Resource, Default)]
+struct Requests {
+    submission: Option<Task<Result<String, String>>>,
+    scores: Option<Task<Result<Tables, String>>>,
+}
+
+pub fn plugin(app: &mut App) {
+    let leaderboard_settings = config::load::<LeaderboardSettings>(app, "leaderboard");
+    app.init_resource::<MoveLog>()
+        .init_resource::<Requests>()
+        .insert_resource(leaderboard_settings)
+        .add_systems(OnEnter(GameStates::GameOver), submit_game.run_if(classic))
+        .add_systems(OnEnter(GameStates::Records), fetch_scores)
+        .add_systems(
+            Update,
+            (
+                log_moves
+                    .after(track_cursor)
+                    .before(open_cells)
+                    .run_if(in_state(PauseState::Running)),
+                finish_submission,
+                show_scores,
+            ),
+        );
+}
+
+/// Table name of the boards the game deals with `variant`.
+pub fn difficulty(board: &BoardSettings, variant: Variant) -> String {
+    leaderboard::difficulty(board.width, board.height, board.mines, variant)
+}
+
+fn log_moves(
+    mouse_btn: Res<ButtonInput<MouseButton>>,
+    cursor_world_pos: Res<CursorWorldPos>,
+    timer: Res<GameTimer>,
+    minefield_query: Query<&MineField>,
+    mut log: ResMut<MoveLog>,
+) {
+    let flag = match (
+        mouse_btn.just_pressed(MouseButton::Left),
+        mouse_btn.just_pressed(MouseButton::Right),
+    ) {
+        (true, false) => false,
+        (false, true) => true,
+        _ => return,
+    };
+    let Ok(minefield) = minefield_query.single() else {
+        return;
+    };
+    let Some((row, column)) = cursor_world_pos
+        .0
+        .and_then(|cursor_world_pos| find_cell_under_cursor(minefield, cursor_world_pos))
+    else {
+        return;
+    };
+
+    log.moves.push(Move {
+        row,
+        column,
+        flag,
+        time: timer.0.elapsed_secs(),
+    });
+}
+
+fn submit_game(
+    outcome: Res<Outcome>,
+    board: Res<BoardSettings>,
+    settings: Res<LeaderboardSettings>,
+    log: Res<MoveLog>,
+    minefield_query: Query<&MineField>,
+    mut requests: ResMut<Requests>,
+) {
+    let (Outcome::Won { time, .. }, Some(server), Some(seed), Ok(minefield)) = (
+        *outcome,
+        settings.server.clone(),
+        log.seed,
+        minefield_query.single(),
+    ) else {
+        return;
+    };
+
+    let game = Game {
+        name: settings.name.clone(),
+        width: minefield.width,
+        height: minefield.height,
+        mines: board.mines,
+        max_mines_per_cell: minefield.variant.max_mines_per_cell,
+        anti_mines: minefield.variant.anti_mines,
+        stencil: minefield.variant.stencil,
+        seed,
+        moves: log.moves.clone(),
+        time,
+    };
+    let submission = game
+        .sign(&settings.secret)
+        .and_then(|submission| ron::to_string(&submission).map_err(|err| err.to_string()));
+    let Ok(body) = submission else {
+        return;
+    };
+
+    requests.submission = Some(
+        IoTaskPool::get().spawn(async move { request(&server, "POST", "/scores", &body) }),
+    );
+}
+
+fn finish_submission(
+    mut requests: ResMut<Requests>,
+    mut announcements: EventWriter<Announcement>,
+) {
+    let Some(task) = &mut requests.submission else {
+        return;
+    };
+    let Some(result) = block_on(future::poll_once(task)) else {
+        return;
+    };
+    requests.submission = None;
+
+    match result {
+        Ok(place) if place != "-" => {
+            announcements.write(Announcement(format!("Number {place} on the leaderboard")));
+        }
+        Ok(_) => {}
+        Err(err) => warn!("Leaderboard didn't take the game: {err}"),
+    }
+}
+
+fn fetch_scores(settings: Res<LeaderboardSettings>, mut requests: ResMut<Requests>) {
+    let Some(server) = settings.server.clone() else {
+        return;
+    };
+    requests.scores = Some(IoTaskPool::get().spawn(async move {
+        request(&server, "GET", "/scores", "")
+            .and_then(|body| ron::from_str(&body).map_err(|err| err.to_string()))
+    }));
+}
+
+fn show_scores(
+    board: Res<BoardSettings>,
+    variant: Res<Variant>,
+    mut requests: ResMut<Requests>,
+    mut text_query: Query<&mut Text, With<OnlineScores>>,
+) {
+    let Some(task) = &mut requests.scores else {
+        return;
+    };
+    let Some(result) = block_on(future::poll_once(task)) else {
+        return;
+    };
+    requests.scores = None;
+
+    let table = match result {
+        Ok(tables) => tables
+            .get(&difficulty(&board, *variant))
+            .filter(|entries| !entries.is_empty())
+            .map_or("No scores yet".to_string(), |entries| {
+                entries
+                    .iter()
+                    .enumerate()
+                    .map(|(place, entry)| {
+                        format!("{}. {} {:.1} s", place + 1, entry.name, entry.time)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }),
+        Err(err) => format!("Server unavailable: {err}"),
+    };
+    for mut text in &mut text_query {
+        text.0.clone_from(&table);
+    }
+}
+
+/// A bare HTTP/1.1 request, the body of a 2xx answer or what went wrong.
+fn request(server: &str, method: &str, path: &str, body: &str) -> Result<String, String> {
+    let host = server
+        .strip_prefix("http://")
+        .ok_or("only http:// servers are supported")?
+        .trim_end_matches('/');
+    let address = host
+        .to_socket_addrs()
+        .map_err(|err| err.to_string())?
+        .next()
+        .ok_or(format!("{host} has no address"))?;
+
+    let mut stream =
+        TcpStream::connect_timeout(&address, TIMEOUT).map_err(|err| err.to_string())?;
+    let mut response = String::new();
+    stream
+        .set_read_timeout(Some(TIMEOUT))
+        .and_then(|()| {
+            write!(
+                stream,
+                "{method} {path} HTTP/1.1\r\nHost: {host}\r\n\
+Content-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+        })
+        .and_then(|()| stream.read_to_string(&mut response))
+        .map_err(|err| err.to_string())?;
+
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or("malformed response")?;
+    let status = head.split_whitespace().nth(1).unwrap_or_default();
+    if status.starts_with('2') {
+        Ok(body.to_string())
+    } else {
+        Err(format!("{status} {body}"))
+    }
+}
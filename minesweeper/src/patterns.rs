@@ -0,0 +1,117 @@
+//! Small board fragments built around the classic patterns, for drilling them one at a time.
+
+use std::collections::HashMap;
+
+use crate::{
+    minefield::{MineField, Variant},
+    solver,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Pattern {
+    OneTwoOne,
+    OneTwoTwoOne,
+    /// Two 1s starting at the board's edge, the cells past the second are safe.
+    OneOneEdge,
+    /// A 2 beside a 1 that sees all but one of its cells, so that cell is a mine.
+    Triangle,
+}
+
+impl Pattern {
+    pub const ALL: [Pattern; 4] = [
+        Pattern::OneTwoOne,
+        Pattern::OneTwoTwoOne,
+        Pattern::OneOneEdge,
+        Pattern::Triangle,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Pattern::OneTwoOne => "1-2-1",
+            Pattern::OneTwoTwoOne => "1-2-2-1",
+            Pattern::OneOneEdge => "1-1 edge",
+            Pattern::Triangle => "Triangle",
+        }
+    }
+
+    /// Fragments showing the pattern, top row first: `.` is open, `*` a hidden mine and `o`
+    /// a hidden safe cell.
+    fn templates(self) -> &'static [&'static [&'static str]] {
+        match self {
+            Pattern::OneTwoOne => &[
+                &["ooooooooo", "*oo*o*oo*", "........."],
+                &["ooooooo", "o*o*oo*", "......."],
+            ],
+            Pattern::OneTwoTwoOne => &[
+                &["oooooooo", "*oo**oo*", "........"],
+                &["ooooooo", "o**oo*o", "......."],
+            ],
+            Pattern::OneOneEdge => &[&["oooo", "*ooo", "..oo"], &["oooo", "o*oo", "..oo"]],
+            Pattern::Triangle => &[
+                &["ooooo", "o**o*", "....."],
+                &["oooooooo", "o**o*oo*", "........"],
+            ],
+        }
+    }
+}
+
+/// A fragment to solve and what it proves.
+pub struct Drill {
+    pub pattern: Pattern,
+    pub minefield: MineField,
+    /// Hidden cells the numbers prove mined (`true`) or safe, the ones the player must mark.
+    pub answers: HashMap<(usize, usize), bool>,
+}
+
+impl Drill {
+    /// A random fragment for `pattern`, turned and mirrored at random so the pattern gets
+    /// learnt in every direction.
+    pub fn new(pattern: Pattern) -> Drill {
+        let templates = pattern.templates();
+        let template = templates[rand::random_range(0..templates.len())];
+
+        // Rows are stored bottom up, so the template is read in reverse
+        let mut rows: Vec<Vec<char>> = template
+            .iter()
+            .rev()
+            .map(|row| row.chars().collect())
+            .collect();
+        for _ in 0..rand::random_range(0..4) {
+            rows = turn(&rows);
+        }
+        if rand::random() {
+            rows.iter_mut().for_each(|row| row.reverse());
+        }
+
+        let mines: Vec<Vec<i32>> = rows
+            .iter()
+            .map(|row| row.iter().map(|&cell| i32::from(cell == '*')).collect())
+            .collect();
+        let mut minefield = MineField::from_mines(&mines, Variant::CLASSIC);
+        for (cell, &kind) in minefield
+            .cells
+            .iter_mut()
+            .flatten()
+            .zip(rows.iter().flatten())
+        {
+            cell.is_open = kind == '.';
+        }
+
+        let answers = solver::deduce(&minefield)
+            .into_iter()
+            .map(|(cell, mines)| (cell, mines != 0))
+            .collect();
+        Drill {
+            pattern,
+            minefield,
+            answers,
+        }
+    }
+}
+
+/// A quarter turn: each column becomes a row.
+fn turn(rows: &[Vec<char>]) -> Vec<Vec<char>> {
+    (0..rows[0].len())
+        .map(|column| rows.iter().rev().map(|row| row[column]).collect())
+        .collect()
+}
@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+
+use crate::{
+    GameStates, Outcome, cell_position, cell_size, minefield::MineField, solver::PostMortem,
+};
+
+const SAFE_COLOR: Color = Color::srgba(0., 0.8, 0., 0.4);
+const GUESS_COLOR: Color = Color::srgba(1., 0.8, 0., 0.6);
+
+/// What the player could have known before the click that lost the last game, filled in
+/// by `open_cells` when a mine goes off.
+#[derive(Resource, Default)]
+pub struct LossReport(pub Option<PostMortem>);
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<LossReport>()
+        .add_systems(OnEnter(GameStates::GameOver), spawn_highlights);
+}
+
+/// Marks the cells that were provably safe, or the best guess when there were none.
+fn spawn_highlights(
+    mut cmd: Commands,
+    outcome: Res<Outcome>,
+    report: Res<LossReport>,
+    minefield_query: Query<&MineField>,
+) {
+    let (Outcome::Lost, Some(report), Ok(minefield)) =
+        (*outcome, &report.0, minefield_query.single())
+    else {
+        return;
+    };
+
+    let cell_size = cell_size(minefield);
+    let safe = report.safe_cells.iter().map(|&cell| (cell, SAFE_COLOR));
+    let guess = report
+        .safest_guess
+        .filter(|_| report.was_forced())
+        .map(|(cell, _)| (cell, GUESS_COLOR));
+    for ((row, col), color) in safe.chain(guess) {
+        cmd.spawn((
+            Sprite {
+                color,
+                custom_size: Some(Vec2::splat(cell_size * 0.9)),
+                ..default()
+            },
+            Transform::from_translation(cell_position(minefield, row, col).extend(1.5)),
+            StateScoped(GameStates::GameOver),
+        ));
+    }
+}
+
+/// The report in words, a line each for the verdict and the odds.
+pub fn describe(report: &PostMortem) -> [String; 2] {
+    let percent = |chance: f64| format!("{:.0}%", chance * 100.);
+
+    let verdict = match report.safe_cells.len() {
+        0 if !report.settled => {
+            "Too big a board to tell whether any cell was provably safe".to_string()
+        }
+        0 if report.was_forced() => {
+            "Unavoidable: no cell was provably safe, a guess was forced".to_string()
+        }
+        0 => "Avoidable: that cell was proven to be a mine".to_string(),
+        1 => "Avoidable: 1 cell was provably safe (green)".to_string(),
+        safe => format!("Avoidable: {safe} cells were provably safe (green)"),
+    };
+    let odds = match (report.fatal_probability, report.safest_guess) {
+        (None, _) => "Too many unknowns to count the odds".to_string(),
+        (Some(fatal), Some((_, safest))) if report.was_forced() => format!(
+            "That cell had a {} chance of a mine, the safest guess {} (yellow)",
+            percent(fatal),
+            percent(safest),
+        ),
+        (Some(fatal), _) => format!("That cell had a {} chance of a mine", percent(fatal)),
+    };
+    [verdict, odds]
+}
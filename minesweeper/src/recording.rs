@@ -0,0 +1,46 @@
+use bevy::prelude::*;
+
+use crate::{
+    GameStates, GameTimer, Outcome, animation::CellAnimations, config::ConfigDir,
+    minefield::MineField, replay::Replay, start_new_game,
+};
+
+/// The game being played, restarted by `start_new_game` and saved to `replay.ron` in the
+/// config dir once it's over, for `export-gif` to pick up.
+#[derive(Resource, Default)]
+pub struct ReplayLog(pub Replay);
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<ReplayLog>()
+        .add_systems(OnEnter(GameStates::GameOver), save_replay)
+        .add_systems(
+            Update,
+            record_moves
+                .run_if(in_state(GameStates::Playing))
+                .after(start_new_game),
+        );
+}
+
+/// The move that ends a game still lands here, the state only changes on the next frame.
+fn record_moves(
+    minefield_query: Query<&MineField, Changed<MineField>>,
+    timer: Res<GameTimer>,
+    mut log: ResMut<ReplayLog>,
+) {
+    if let Ok(minefield) = minefield_query.single() {
+        log.0.record(minefield, timer.0.elapsed_secs());
+    }
+}
+
+fn save_replay(
+    outcome: Res<Outcome>,
+    animations: Res<CellAnimations>,
+    mut log: ResMut<ReplayLog>,
+    config: Res<ConfigDir>,
+) {
+    log.0.exploded = match *outcome {
+        Outcome::Lost => animations.exploded,
+        Outcome::Won { .. } => None,
+    };
+    config.save("replay", &log.0);
+}
@@ -0,0 +1,73 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BoardSettings, challenge::ChallengeMode, config::ConfigDir, leaderboard,
+    minefield::Variant,
+};
+
+/// Best winning times in seconds, saved to `records.ron` in the config dir.
+#[derive(Resource, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Records {
+    /// Keyed by `leaderboard::difficulty`, so only boards alike are compared.
+    best_times: BTreeMap<String, f32>,
+    /// Most boards cleared in one run, keyed by `ChallengeMode::name`.
+    challenges: BTreeMap<String, u32>,
+}
+
+fn difficulty(board: &BoardSettings, variant: Variant) -> String {
+    leaderboard::difficulty(board.width, board.height, board.mines, variant)
+}
+
+impl Records {
+    pub fn best_time(&self, board: &BoardSettings, variant: Variant) -> Option<f32> {
+        self.best_times.get(&difficulty(board, variant)).copied()
+    }
+
+    /// Stores `time` if it beats the best on random boards like `board`, returns whether
+    /// it did.
+    pub fn submit(
+        &mut self,
+        board: &BoardSettings,
+        variant: Variant,
+        time: f32,
+        config: &ConfigDir,
+    ) -> bool {
+        if self
+            .best_time(board, variant)
+            .is_some_and(|best| best <= time)
+        {
+            return false;
+        }
+
+        self.best_times.insert(difficulty(board, variant), time);
+        config.save("records", self);
+        true
+    }
+
+    pub fn best_challenge(&self, mode: ChallengeMode) -> Option<u32> {
+        self.challenges.get(mode.name()).copied()
+    }
+
+    /// Stores `cleared` if it beats the mode's best, returns whether it did.
+    pub fn submit_challenge(
+        &mut self,
+        mode: ChallengeMode,
+        cleared: u32,
+        config: &ConfigDir,
+    ) -> bool {
+        if self
+            .best_challenge(mode)
+            .is_some_and(|best| best >= cleared)
+        {
+            return false;
+        }
+
+        self.challenges.insert(mode.name().to_string(), cleared);
+        config.save("records", self);
+        true
+    }
+}
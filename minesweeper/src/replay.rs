@@ -0,0 +1,110 @@
+//! Games recorded as the board states the player saw, one per move.
+
+use serde::{Deserialize, Serialize};
+
+use crate::minefield::{MineField, Stencil, Variant};
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Replay {
+    pub max_mines_per_cell: i32,
+    pub anti_mines: bool,
+    pub stencil: Stencil,
+    /// Mines in each cell by row, as `MineField::mines` gives them.
+    pub mines: Vec<Vec<i32>>,
+    /// The mine that ended the game, if one did.
+    pub exploded: Option<(usize, usize)>,
+    pub frames: Vec<Frame>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Frame {
+    /// Seconds into the game.
+    pub time: f32,
+    /// A string per row and a character per cell: `.` hidden, `o` open, `1` to `9` for
+    /// flags and `a` to `i` for anti-mine flags.
+    pub rows: Vec<String>,
+}
+
+impl Replay {
+    /// Starts recording `minefield`, its current state being the first frame.
+    pub fn new(minefield: &MineField) -> Replay {
+        let mut replay = Replay {
+            max_mines_per_cell: minefield.variant.max_mines_per_cell,
+            anti_mines: minefield.variant.anti_mines,
+            stencil: minefield.variant.stencil,
+            mines: minefield.mines(),
+            exploded: None,
+            frames: Vec::new(),
+        };
+        replay.record(minefield, 0.);
+        replay
+    }
+
+    /// Adds the board's state as a frame, unless nothing visible changed since the last one.
+    pub fn record(&mut self, minefield: &MineField, time: f32) {
+        let rows: Vec<String> = minefield
+            .cells
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| match (cell.is_open, cell.flags) {
+                        (true, _) => 'o',
+                        (false, 0) => '.',
+                        (false, flags) if flags > 0 => flag_char(b'0', flags),
+                        (false, flags) => flag_char(b'a' - 1, -flags),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        if self.frames.last().is_none_or(|last| last.rows != rows) {
+            self.frames.push(Frame { time, rows });
+        }
+    }
+
+    /// Names the board, the same for every game played on the same mines and variant.
+    pub fn layout(&self) -> String {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&[self.max_mines_per_cell as u8, self.anti_mines as u8]);
+        // Classic boards hash as they did before stencils, keeping their saved ghosts
+        if self.stencil != Stencil::KING {
+            for (dy, dx) in self.stencil.offsets() {
+                hasher.update(&[dy as u8, dx as u8]);
+            }
+        }
+        for row in &self.mines {
+            for mines in row {
+                hasher.update(&mines.to_le_bytes());
+            }
+            hasher.update(b"\n");
+        }
+        hasher.finalize().to_hex()[..16].to_string()
+    }
+
+    /// The board as it was at `frame`.
+    pub fn minefield(&self, frame: usize) -> MineField {
+        let variant = Variant {
+            max_mines_per_cell: self.max_mines_per_cell.max(1),
+            anti_mines: self.anti_mines,
+            stencil: self.stencil,
+        };
+        let mut minefield = MineField::from_mines(&self.mines, variant);
+
+        for (cells, row) in minefield.cells.iter_mut().zip(&self.frames[frame].rows) {
+            for (cell, state) in cells.iter_mut().zip(row.bytes()) {
+                cell.is_open = state == b'o';
+                cell.flags = match state {
+                    b'1'..=b'9' => i32::from(state - b'0'),
+                    b'a'..=b'i' => -i32::from(state - b'a' + 1),
+                    _ => 0,
+                };
+            }
+        }
+        minefield
+    }
+}
+
+fn flag_char(one_before: u8, count: i32) -> char {
+    char::from(one_before + count.clamp(1, 9) as u8)
+}
@@ -0,0 +1,296 @@
+use std::{fs, path::Path};
+
+use bevy::{asset::io::file::FileAssetReader, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{ControlPanel, config::ConfigDir};
+
+/// Custom skins are folders under this one, each with a `skin.ron` manifest.
+const SKINS_DIR: &str = "skins";
+
+#[derive(Resource)]
+pub struct CellTextures {
+    pub hidden: Handle<Image>,
+    pub revealed: Handle<Image>,
+    pub mine: Handle<Image>,
+    pub exploded: Handle<Image>,
+    pub flag: Handle<Image>,
+    /// Textures for 1 and up, counts without a texture are drawn as text.
+    pub numbers: Vec<Handle<Image>>,
+}
+
+#[derive(Resource)]
+pub struct ControlPanelTextures {
+    pub casual: Handle<Image>,
+    pub scared: Handle<Image>,
+    pub dead: Handle<Image>,
+}
+
+#[derive(Resource, Clone)]
+pub struct SkinColours {
+    pub board: Color,
+    pub panel: Color,
+    /// Multiplied into every cell and face sprite.
+    pub tint: Color,
+    /// Counts written as text on top of cells.
+    pub label: Color,
+    pub anti_label: Color,
+}
+
+/// Marks the sprite drawn behind the minefield.
+#[derive(Component)]
+pub struct BoardBackdrop;
+
+/// `skins/<folder>/skin.ron` as written by skin authors. Image paths are relative to the
+/// skin's folder and anything left out or missing on disk comes from the built-in skin.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SkinManifest {
+    name: Option<String>,
+    hidden: Option<String>,
+    revealed: Option<String>,
+    mine: Option<String>,
+    exploded: Option<String>,
+    flag: Option<String>,
+    /// Textures for 1 and up.
+    numbers: Vec<String>,
+    casual_face: Option<String>,
+    scared_face: Option<String>,
+    dead_face: Option<String>,
+    background: Option<String>,
+    colours: ColoursManifest,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ColoursManifest {
+    clear: Option<[f32; 3]>,
+    board: Option<[f32; 3]>,
+    panel: Option<[f32; 3]>,
+    tint: Option<[f32; 3]>,
+    label: Option<[f32; 3]>,
+    anti_label: Option<[f32; 3]>,
+}
+
+/// A skin with every path resolved against the asset folder.
+#[derive(Clone)]
+pub struct Skin {
+    pub name: String,
+    hidden: String,
+    revealed: String,
+    mine: String,
+    exploded: String,
+    flag: String,
+    numbers: Vec<String>,
+    casual_face: String,
+    scared_face: String,
+    dead_face: String,
+    background: Option<String>,
+    clear: Color,
+    colours: SkinColours,
+}
+
+impl Skin {
+    fn builtin() -> Skin {
+        Skin {
+            name: "Classic".into(),
+            hidden: "cells/unknown.png".into(),
+            revealed: "cells/empty.png".into(),
+            mine: "cells/bomb.png".into(),
+            exploded: "cells/bomb_exploded.png".into(),
+            flag: "cells/flag.png".into(),
+            numbers: (1..=8)
+                .map(|number| format!("cells/{number}.png"))
+                .collect(),
+            casual_face: "control_panel/casual_face.png".into(),
+            scared_face: "control_panel/scared_face.png".into(),
+            dead_face: "control_panel/dead_face.png".into(),
+            background: None,
+            clear: Color::srgb(1., 1., 1.),
+            colours: SkinColours {
+                board: Color::srgb(0.5, 0.5, 0.5),
+                panel: Color::srgb(0., 0., 0.),
+                tint: Color::srgb(0.7, 0.7, 0.7),
+                label: Color::srgb(0., 0., 0.6),
+                anti_label: Color::srgb(0.8, 0., 0.),
+            },
+        }
+    }
+
+    fn from_manifest(assets_dir: &Path, folder: &str, manifest: SkinManifest) -> Skin {
+        let builtin = Skin::builtin();
+        let resolve = |file: Option<String>, fallback: String| {
+            let Some(file) = file else {
+                return fallback;
+            };
+            let path = format!("{SKINS_DIR}/{folder}/{file}");
+            if assets_dir.join(&path).is_file() {
+                path
+            } else {
+                warn!("Skin {folder} is missing {path}, using the built-in one");
+                fallback
+            }
+        };
+        let colour = |rgb: Option<[f32; 3]>, fallback: Color| {
+            rgb.map_or(fallback, |[r, g, b]| Color::srgb(r, g, b))
+        };
+
+        let mut numbers = manifest.numbers.into_iter();
+        let colours = manifest.colours;
+        Skin {
+            name: manifest.name.unwrap_or_else(|| folder.to_string()),
+            hidden: resolve(manifest.hidden, builtin.hidden),
+            revealed: resolve(manifest.revealed, builtin.revealed),
+            mine: resolve(manifest.mine, builtin.mine),
+            exploded: resolve(manifest.exploded, builtin.exploded),
+            flag: resolve(manifest.flag, builtin.flag),
+            numbers: builtin
+                .numbers
+                .into_iter()
+                .map(|fallback| resolve(numbers.next(), fallback))
+                .collect(),
+            casual_face: resolve(manifest.casual_face, builtin.casual_face),
+            scared_face: resolve(manifest.scared_face, builtin.scared_face),
+            dead_face: resolve(manifest.dead_face, builtin.dead_face),
+            background: manifest
+                .background
+                .map(|file| resolve(Some(file), String::new()))
+                .filter(|path| !path.is_empty()),
+            clear: colour(colours.clear, builtin.clear),
+            colours: SkinColours {
+                board: colour(colours.board, builtin.colours.board),
+                panel: colour(colours.panel, builtin.colours.panel),
+                tint: colour(colours.tint, builtin.colours.tint),
+                label: colour(colours.label, builtin.colours.label),
+                anti_label: colour(colours.anti_label, builtin.colours.anti_label),
+            },
+        }
+    }
+}
+
+/// Every skin found at startup, the built-in one first. Changing `current` swaps the
+/// textures on the next frame.
+#[derive(Resource)]
+pub struct Skins {
+    pub skins: Vec<Skin>,
+    pub current: usize,
+}
+
+impl Skins {
+    pub fn current(&self) -> &Skin {
+        &self.skins[self.current]
+    }
+
+    pub fn select_next(&mut self, config: &ConfigDir) {
+        self.current = (self.current + 1) % self.skins.len();
+        config.save(
+            "skin",
+            &SkinChoice {
+                name: Some(self.current().name.clone()),
+            },
+        );
+    }
+}
+
+/// Saved to `skin.ron` in the config dir.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct SkinChoice {
+    name: Option<String>,
+}
+
+/// The built-in skin and every one in the assets folder, the order `S` cycles through.
+pub fn available() -> Vec<Skin> {
+    let assets_dir = FileAssetReader::get_base_path().join("assets");
+    let mut skins = vec![Skin::builtin()];
+
+    let mut folders: Vec<String> = fs::read_dir(assets_dir.join(SKINS_DIR))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    folders.sort();
+
+    for folder in folders {
+        let path = assets_dir.join(SKINS_DIR).join(&folder).join("skin.ron");
+        let manifest = match fs::read_to_string(&path).map(|contents| ron::from_str(&contents))
+        {
+            Ok(Ok(manifest)) => manifest,
+            Ok(Err(err)) => {
+                warn!("Skipping skin {}: {err}", path.display());
+                continue;
+            }
+            Err(_) => continue,
+        };
+        skins.push(Skin::from_manifest(&assets_dir, &folder, manifest));
+    }
+    skins
+}
+
+/// Skin picked on the command line, over the one saved last time.
+#[derive(Resource, Default)]
+pub struct StartSkin(pub Option<String>);
+
+pub fn load_skins(mut cmd: Commands, start: Res<StartSkin>, config: Res<ConfigDir>) {
+    let skins = available();
+    let name = start
+        .0
+        .clone()
+        .or_else(|| config.load::<SkinChoice>("skin").name);
+    let current = skins
+        .iter()
+        .position(|skin| Some(&skin.name) == name.as_ref())
+        .unwrap_or(0);
+    cmd.insert_resource(Skins { skins, current });
+}
+
+pub fn switch_skin(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    config: Res<ConfigDir>,
+    mut skins: ResMut<Skins>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyS) {
+        skins.select_next(&config);
+    }
+}
+
+/// Swaps in the textures and colours of the selected skin, runs whenever `Skins` changes.
+pub fn apply_skin(
+    mut cmd: Commands,
+    assets: Res<AssetServer>,
+    skins: Res<Skins>,
+    mut backdrop: Query<&mut Sprite, (With<BoardBackdrop>, Without<ControlPanel>)>,
+    mut panel: Query<&mut Sprite, (With<ControlPanel>, Without<BoardBackdrop>)>,
+) {
+    let skin = skins.current();
+
+    cmd.insert_resource(CellTextures {
+        hidden: assets.load(&skin.hidden),
+        revealed: assets.load(&skin.revealed),
+        mine: assets.load(&skin.mine),
+        exploded: assets.load(&skin.exploded),
+        flag: assets.load(&skin.flag),
+        numbers: skin.numbers.iter().map(|path| assets.load(path)).collect(),
+    });
+    cmd.insert_resource(ControlPanelTextures {
+        casual: assets.load(&skin.casual_face),
+        scared: assets.load(&skin.scared_face),
+        dead: assets.load(&skin.dead_face),
+    });
+    cmd.insert_resource(skin.colours.clone());
+    cmd.insert_resource(ClearColor(skin.clear));
+
+    if let Ok(mut sprite) = backdrop.single_mut() {
+        sprite.color = skin.colours.board;
+        sprite.image = skin
+            .background
+            .as_ref()
+            .map(|path| assets.load(path))
+            .unwrap_or_default();
+    }
+    if let Ok(mut sprite) = panel.single_mut() {
+        sprite.color = skin.colours.panel;
+    }
+}
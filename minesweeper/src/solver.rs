@@ -0,0 +1,642 @@
+//! Logic a player could apply to a board, using only what's visible: open cells and their
+//! numbers.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::minefield::{MineField, check_win_condition, reveal_cells};
+
+/// What the numbers around a set of hidden cells say: their mines add up to `sum`.
+struct Constraint {
+    cells: Vec<(usize, usize)>,
+    sum: i32,
+}
+
+/// Hidden cells whose mine count is forced by the open numbers, `0` meaning safe. Rules are
+/// reapplied with what they found until nothing new follows.
+pub fn deduce(minefield: &MineField) -> HashMap<(usize, usize), i32> {
+    let mut steps = usize::MAX;
+    deduce_within(minefield, &mut steps).0
+}
+
+/// Work a deduction may do on a lost click before settling for what it found: each pass
+/// goes over the whole board, so a huge one could otherwise stall the frame.
+const MAX_DEDUCE_STEPS: usize = 1 << 22;
+
+/// `deduce` spending at most `steps`, a cell looked at or a pair of numbers compared each.
+/// Returns what was found and whether it ran to the end rather than out of steps.
+fn deduce_within(
+    minefield: &MineField,
+    steps: &mut usize,
+) -> (HashMap<(usize, usize), i32>, bool) {
+    let mut known = HashMap::new();
+    loop {
+        let Some(found) = deduce_step(minefield, &known, steps) else {
+            return (known, false);
+        };
+        if found.is_empty() {
+            return (known, true);
+        }
+        known.extend(found);
+    }
+}
+
+fn deduce_step(
+    minefield: &MineField,
+    known: &HashMap<(usize, usize), i32>,
+    steps: &mut usize,
+) -> Option<HashMap<(usize, usize), i32>> {
+    *steps = steps.checked_sub(minefield.width * minefield.height)?;
+    let constraints = constraints(minefield, known);
+    let mut found = HashMap::new();
+
+    for constraint in &constraints {
+        apply_bounds(minefield, constraint, &mut found);
+    }
+    if !found.is_empty() {
+        return Some(found);
+    }
+
+    // Only numbers sharing a hidden cell say anything about each other
+    let mut sharing: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (index, constraint) in constraints.iter().enumerate() {
+        for &cell in &constraint.cells {
+            sharing.entry(cell).or_default().push(index);
+        }
+    }
+    for (index, a) in constraints.iter().enumerate() {
+        let mut others: Vec<usize> = a
+            .cells
+            .iter()
+            .flat_map(|cell| &sharing[cell])
+            .copied()
+            .filter(|&other| other != index)
+            .collect();
+        others.sort_unstable();
+        others.dedup();
+        *steps = steps.checked_sub(others.len())?;
+        for other in others {
+            apply_overlap(minefield, a, &constraints[other], &mut found);
+        }
+    }
+    Some(found)
+}
+
+/// `b`'s sum less `a`'s is what only `b` covers less what only `a` covers. With nothing
+/// only in `a`, that's the sum of the rest of `b`. Otherwise, when it takes every cell only
+/// in `b` full and every cell only in `a` empty to reach, that's how they are. Each pair is
+/// also tried the other way round, which covers the opposite extreme.
+fn apply_overlap(
+    minefield: &MineField,
+    a: &Constraint,
+    b: &Constraint,
+    found: &mut HashMap<(usize, usize), i32>,
+) {
+    let (only_a, only_b): (Vec<(usize, usize)>, Vec<(usize, usize)>) = (
+        a.cells
+            .iter()
+            .filter(|c| !b.cells.contains(c))
+            .copied()
+            .collect(),
+        b.cells
+            .iter()
+            .filter(|c| !a.cells.contains(c))
+            .copied()
+            .collect(),
+    );
+    if only_b.is_empty() {
+        return;
+    }
+    let difference = b.sum - a.sum;
+    if only_a.is_empty() {
+        let rest = Constraint {
+            cells: only_b,
+            sum: difference,
+        };
+        apply_bounds(minefield, &rest, found);
+        return;
+    }
+
+    let variant = minefield.variant;
+    let most = variant.max_mines_per_cell;
+    let least = if variant.anti_mines { -most } else { 0 };
+    if difference == only_b.len() as i32 * most - only_a.len() as i32 * least {
+        found.extend(only_b.into_iter().map(|cell| (cell, most)));
+        found.extend(only_a.into_iter().map(|cell| (cell, least)));
+    }
+}
+
+fn constraints(
+    minefield: &MineField,
+    known: &HashMap<(usize, usize), i32>,
+) -> Vec<Constraint> {
+    let mut constraints = Vec::new();
+
+    for (row, cells_row) in minefield.cells.iter().enumerate() {
+        for (column, cell) in cells_row.iter().enumerate() {
+            if !cell.is_open || cell.is_mined() {
+                continue;
+            }
+
+            let mut constraint = Constraint {
+                cells: Vec::new(),
+                sum: cell.mines_around,
+            };
+            for (y, x) in minefield.neighbours(row, column) {
+                if minefield.cells[y][x].is_open {
+                    continue;
+                }
+                match known.get(&(y, x)) {
+                    Some(mines) => constraint.sum -= mines,
+                    None => constraint.cells.push((y, x)),
+                }
+            }
+            if !constraint.cells.is_empty() {
+                constraints.push(constraint);
+            }
+        }
+    }
+    constraints
+}
+
+/// Every cell can hold between the variant's least and most mines, so when the others
+/// are at their extremes the sum may leave a cell only one possible count.
+fn apply_bounds(
+    minefield: &MineField,
+    constraint: &Constraint,
+    found: &mut HashMap<(usize, usize), i32>,
+) {
+    let variant = minefield.variant;
+    let most = variant.max_mines_per_cell;
+    let least = if variant.anti_mines { -most } else { 0 };
+    let others = constraint.cells.len() as i32 - 1;
+
+    let min = (constraint.sum - others * most).max(least);
+    let max = (constraint.sum - others * least).min(most);
+    if min == max {
+        for &cell in &constraint.cells {
+            found.insert(cell, min);
+        }
+    }
+}
+
+/// Where the solver makes its first click: a safe cell that cascades if there is one.
+pub fn start_cell(minefield: &MineField) -> Option<(usize, usize)> {
+    let safe_cells = || {
+        (0..minefield.height)
+            .flat_map(|row| (0..minefield.width).map(move |column| (row, column)))
+            .filter(|&(row, column)| !minefield.cells[row][column].is_mined())
+    };
+
+    safe_cells()
+        .find(|&(row, column)| minefield.cells[row][column].mined_neighbours == 0)
+        .or_else(|| safe_cells().next())
+}
+
+/// Plays the board from `start` on deductions alone, opening a safe cell whenever logic
+/// runs dry. Returns the cells that had to be guessed that way, so an empty list means the
+/// board is solvable without guessing. The first click at `start` isn't counted. Gives up
+/// with `None` past `steps`, counted like `deduce_within` does.
+pub fn guesses_needed(
+    minefield: &MineField,
+    start: (usize, usize),
+    mut steps: usize,
+) -> Option<Vec<(usize, usize)>> {
+    let mut board = MineField::from_mines(&minefield.mines(), minefield.variant);
+    let mut guesses = Vec::new();
+    reveal_cells(&mut board, start.1, start.0);
+
+    while !check_win_condition(&board) {
+        let (known, finished) = deduce_within(&board, &mut steps);
+        if !finished {
+            return None;
+        }
+        let safe: Vec<(usize, usize)> = known
+            .into_iter()
+            .filter(|&(_, mines)| mines == 0)
+            .map(|(cell, _)| cell)
+            .collect();
+        if !safe.is_empty() {
+            for (row, column) in safe {
+                reveal_cells(&mut board, column, row);
+            }
+            continue;
+        }
+
+        // Prefer a guess on the edge of the open area, like a player would make
+        steps = steps.checked_sub(board.width * board.height)?;
+        let hidden_safe: Vec<(usize, usize)> = (0..board.height)
+            .flat_map(|row| (0..board.width).map(move |column| (row, column)))
+            .filter(|&(row, column)| {
+                let cell = &board.cells[row][column];
+                !cell.is_open && !cell.is_mined()
+            })
+            .collect();
+        let Some(&guess) = hidden_safe
+            .iter()
+            .find(|&&(row, column)| {
+                board
+                    .neighbours(row, column)
+                    .any(|(y, x)| board.cells[y][x].is_open)
+            })
+            .or(hidden_safe.first())
+        else {
+            break;
+        };
+
+        guesses.push(guess);
+        reveal_cells(&mut board, guess.1, guess.0);
+    }
+
+    Some(guesses)
+}
+
+/// Enumerating a tangle of numbers doubles in cost with every cell, past this many it's
+/// not attempted.
+const MAX_COMPONENT_CELLS: usize = 32;
+/// Cells tried across every tangle before counting gives up. Variants with several mine
+/// counts a cell can hold grow faster than doubling, so the cell cap alone isn't enough to
+/// keep a lost click from stalling a frame.
+const MAX_SEARCH_STEPS: usize = 1 << 20;
+
+/// Arrangements of one group of hidden cells whose numbers overlap, keyed by how many
+/// mines each arrangement uses.
+#[derive(Default)]
+struct Component {
+    cells: Vec<(usize, usize)>,
+    /// Indices of the constraints on `cells`.
+    constraints: Vec<usize>,
+    /// `(arrangements, arrangements mining each cell)` per mine total.
+    solutions: HashMap<i32, (f64, Vec<f64>)>,
+}
+
+/// Chance of each hidden cell holding a mine, knowing only the open numbers and that the
+/// board holds `total_mines` mines, counted the way `Cell::mines` does but ignoring signs.
+/// Every arrangement that fits is taken as equally likely, which is exact for classic
+/// boards. Returns `None` when the numbers tangle up too many cells to count, or take too
+/// long to.
+pub fn mine_probabilities(
+    minefield: &MineField,
+    total_mines: i32,
+) -> Option<HashMap<(usize, usize), f64>> {
+    let constraints = constraints(minefield, &HashMap::new());
+    let mut components = split_components(&constraints);
+    let mut steps = MAX_SEARCH_STEPS;
+    for component in &mut components {
+        if component.cells.len() > MAX_COMPONENT_CELLS
+            || !enumerate(minefield, &constraints, component, &mut steps)
+        {
+            return None;
+        }
+    }
+
+    let frontier: HashSet<(usize, usize)> = components
+        .iter()
+        .flat_map(|component| component.cells.iter().copied())
+        .collect();
+    let interior: Vec<(usize, usize)> = (0..minefield.height)
+        .flat_map(|row| (0..minefield.width).map(move |column| (row, column)))
+        .filter(|&(row, column)| !minefield.cells[row][column].is_open)
+        .filter(|cell| !frontier.contains(cell))
+        .collect();
+
+    // Ways to spread the leftover mines over the interior, with and without one given cell
+    let remaining = total_mines.max(0) as usize;
+    let values = cell_values(minefield);
+    let (fewer, fewer_scale) =
+        spread_ways(&values, interior.len().saturating_sub(1), remaining);
+    let (all, all_scale) = spread_ways(&values, interior.len(), remaining);
+    let interior_ways =
+        |mines: i32| usize::try_from(remaining as i32 - mines).map_or(0., |left| all[left]);
+
+    // Each component's odds weigh every other's arrangements
+    let work = components.len().pow(2).saturating_mul(remaining + 1);
+    if work > steps {
+        return None;
+    }
+    let totals = frontier_totals(&components, None, remaining);
+    let total: f64 = totals
+        .iter()
+        .enumerate()
+        .map(|(mines, weight)| weight * interior_ways(mines as i32))
+        .sum();
+    if total <= 0. || !total.is_finite() {
+        return None;
+    }
+
+    let mut probabilities = HashMap::new();
+    for (index, component) in components.iter().enumerate() {
+        let others = frontier_totals(&components, Some(index), remaining);
+        let mut mined = vec![0.; component.cells.len()];
+        for (&mines, (_, per_cell)) in &component.solutions {
+            for (other_mines, weight) in others.iter().enumerate() {
+                let ways = weight * interior_ways(mines + other_mines as i32);
+                for (mined, count) in mined.iter_mut().zip(per_cell) {
+                    *mined += count * ways;
+                }
+            }
+        }
+        for (&cell, mined) in component.cells.iter().zip(mined) {
+            probabilities.insert(cell, mined / total);
+        }
+    }
+
+    if !interior.is_empty() {
+        let empty: f64 = totals
+            .iter()
+            .enumerate()
+            .filter_map(|(mines, weight)| {
+                let left = usize::try_from(remaining as i32 - mines as i32).ok()?;
+                Some(weight * fewer[left])
+            })
+            .sum::<f64>()
+            * (fewer_scale - all_scale).exp();
+        for cell in interior {
+            probabilities.insert(cell, 1. - empty / total);
+        }
+    }
+
+    Some(probabilities)
+}
+
+/// Mine counts a single cell can hold under the board's variant.
+fn cell_values(minefield: &MineField) -> Vec<i32> {
+    let most = minefield.variant.max_mines_per_cell;
+    let least = if minefield.variant.anti_mines {
+        -most
+    } else {
+        0
+    };
+    (least..=most).collect()
+}
+
+/// Groups hidden cells sharing a number, since only then do their arrangements depend on
+/// each other.
+fn split_components(constraints: &[Constraint]) -> Vec<Component> {
+    let mut components: Vec<Component> = Vec::new();
+    // The component each cell seen so far went to
+    let mut owners: HashMap<(usize, usize), usize> = HashMap::new();
+
+    for (index, constraint) in constraints.iter().enumerate() {
+        let mut touching: Vec<usize> = constraint
+            .cells
+            .iter()
+            .filter_map(|cell| owners.get(cell).copied())
+            .collect();
+        touching.sort_unstable();
+        touching.dedup();
+
+        let merged = touching.first().copied().unwrap_or_else(|| {
+            components.push(Component::default());
+            components.len() - 1
+        });
+        for &other in touching.iter().skip(1) {
+            let other = std::mem::take(&mut components[other]);
+            for &cell in &other.cells {
+                owners.insert(cell, merged);
+            }
+            components[merged].cells.extend(other.cells);
+            components[merged].constraints.extend(other.constraints);
+        }
+        for &cell in &constraint.cells {
+            if owners.insert(cell, merged).is_none() {
+                components[merged].cells.push(cell);
+            }
+        }
+        components[merged].constraints.push(index);
+    }
+
+    components.retain(|component| !component.cells.is_empty());
+    components
+}
+
+/// Tries every mine count on every cell of `component`, keeping what all numbers allow.
+/// Returns `false` when that took more than the `steps` left.
+fn enumerate(
+    minefield: &MineField,
+    constraints: &[Constraint],
+    component: &mut Component,
+    steps: &mut usize,
+) -> bool {
+    let constraints: Vec<&Constraint> = component
+        .constraints
+        .iter()
+        .map(|&index| &constraints[index])
+        .collect();
+    let mut search = Search {
+        values: cell_values(minefield),
+        // For each cell, the constraints it takes part in
+        links: component
+            .cells
+            .iter()
+            .map(|cell| {
+                (0..constraints.len())
+                    .filter(|&i| constraints[i].cells.contains(cell))
+                    .collect()
+            })
+            .collect(),
+        left: constraints.iter().map(|c| c.sum).collect(),
+        unassigned: constraints.iter().map(|c| c.cells.len() as i32).collect(),
+        assignment: vec![0; component.cells.len()],
+        solutions: HashMap::new(),
+        steps: *steps,
+    };
+
+    // Recursion goes one level per cell, which `MAX_COMPONENT_CELLS` keeps shallow
+    let finished = search.assign(0);
+    *steps = search.steps;
+    component.solutions = search.solutions;
+    finished
+}
+
+struct Search {
+    values: Vec<i32>,
+    links: Vec<Vec<usize>>,
+    /// Mines each constraint still needs.
+    left: Vec<i32>,
+    unassigned: Vec<i32>,
+    assignment: Vec<i32>,
+    solutions: HashMap<i32, (f64, Vec<f64>)>,
+    /// Cells still allowed to be tried.
+    steps: usize,
+}
+
+impl Search {
+    /// Returns `false` once out of steps, leaving `solutions` incomplete.
+    fn assign(&mut self, cell: usize) -> bool {
+        if self.steps == 0 {
+            return false;
+        }
+        self.steps -= 1;
+
+        if cell == self.assignment.len() {
+            let mines = self.assignment.iter().map(|mines| mines.abs()).sum();
+            let (count, per_cell) = self
+                .solutions
+                .entry(mines)
+                .or_insert_with(|| (0., vec![0.; self.assignment.len()]));
+            *count += 1.;
+            for (per_cell, mines) in per_cell.iter_mut().zip(&self.assignment) {
+                if *mines != 0 {
+                    *per_cell += 1.;
+                }
+            }
+            return true;
+        }
+
+        let least = self.values[0];
+        let most = self.values[self.values.len() - 1];
+        for value_index in 0..self.values.len() {
+            let value = self.values[value_index];
+            self.assignment[cell] = value;
+            for &i in &self.links[cell] {
+                self.left[i] -= value;
+                self.unassigned[i] -= 1;
+            }
+
+            let possible = self.links[cell].iter().all(|&i| {
+                (self.unassigned[i] * least..=self.unassigned[i] * most)
+                    .contains(&self.left[i])
+            });
+            let finished = !possible || self.assign(cell + 1);
+
+            for &i in &self.links[cell] {
+                self.left[i] += value;
+                self.unassigned[i] += 1;
+            }
+            if !finished {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Arrangements of every component but `skip` together, indexed by their mine total.
+fn frontier_totals(components: &[Component], skip: Option<usize>, max: usize) -> Vec<f64> {
+    let mut totals = vec![0.; max + 1];
+    totals[0] = 1.;
+
+    for (index, component) in components.iter().enumerate() {
+        if Some(index) == skip {
+            continue;
+        }
+        let mut next = vec![0.; max + 1];
+        for (total, weight) in totals.iter().enumerate() {
+            for (&mines, (count, _)) in &component.solutions {
+                if let Some(next) = next.get_mut(total + mines as usize) {
+                    *next += weight * count;
+                }
+            }
+        }
+        totals = next;
+    }
+    totals
+}
+
+/// Ways `cells` cells can hold each mine total up to `max`. The counts get astronomical, so
+/// they're returned divided by `e^scale`.
+fn spread_ways(values: &[i32], cells: usize, max: usize) -> (Vec<f64>, f64) {
+    let mut per_cell = vec![0.; max + 1];
+    for value in values {
+        if let Some(count) = per_cell.get_mut(value.unsigned_abs() as usize) {
+            *count += 1.;
+        }
+    }
+
+    let mut ways = vec![0.; max + 1];
+    ways[0] = 1.;
+    let mut scale = 0.;
+    for _ in 0..cells {
+        let mut next = vec![0.; max + 1];
+        for (total, weight) in ways.iter().enumerate().filter(|(_, weight)| **weight > 0.) {
+            for (mines, count) in per_cell.iter().enumerate() {
+                if let Some(next) = next.get_mut(total + mines) {
+                    *next += weight * count;
+                }
+            }
+        }
+
+        let biggest = next.iter().copied().fold(0., f64::max);
+        if biggest > 0. {
+            next.iter_mut().for_each(|weight| *weight /= biggest);
+            scale += biggest.ln();
+        }
+        ways = next;
+    }
+    (ways, scale)
+}
+
+/// What a player could have known right before the click that lost the game.
+pub struct PostMortem {
+    /// Hidden cells the open numbers proved safe.
+    pub safe_cells: Vec<(usize, usize)>,
+    /// Whether `safe_cells` holds every such cell, `false` when the board was too big to
+    /// finish looking.
+    pub settled: bool,
+    /// Chance the fatal cell held a mine, `None` when the board was too tangled to count.
+    pub fatal_probability: Option<f64>,
+    /// The hidden cell least likely to hold a mine and its chance of one.
+    pub safest_guess: Option<((usize, usize), f64)>,
+}
+
+impl PostMortem {
+    /// Without a provably safe cell every click was a gamble, so the loss couldn't be
+    /// ruled out, unless the click went on a cell already proven mined.
+    pub fn was_forced(&self) -> bool {
+        self.settled
+            && self.safe_cells.is_empty()
+            && self.fatal_probability.is_none_or(|chance| chance < 1.)
+    }
+}
+
+/// Looks at `before`, the board as the player saw it before clicking, for what they could
+/// have known about `fatal`, the mine that ended the game.
+pub fn post_mortem(before: &MineField, fatal: (usize, usize)) -> PostMortem {
+    let total_mines = before
+        .cells
+        .iter()
+        .flatten()
+        .map(|cell| cell.mines.abs())
+        .sum();
+
+    let mut steps = MAX_DEDUCE_STEPS;
+    let (known, deduced) = deduce_within(before, &mut steps);
+    let probabilities = mine_probabilities(before, total_mines);
+    // Counting weighs every arrangement the numbers allow, so a cell none of them mine is
+    // as safe as one the rules found, and catches what they miss
+    let mut safe_cells: Vec<(usize, usize)> = known
+        .iter()
+        .filter(|&(_, &mines)| mines == 0)
+        .map(|(&cell, _)| cell)
+        .chain(
+            probabilities
+                .iter()
+                .flatten()
+                .filter(|&(_, &chance)| chance == 0.)
+                .map(|(&cell, _)| cell),
+        )
+        .collect();
+    safe_cells.sort();
+    safe_cells.dedup();
+
+    // A cell the numbers prove mined is a sure loss even when counting gave up
+    let fatal_probability = match known.get(&fatal) {
+        Some(&mines) if mines != 0 => Some(1.),
+        _ => probabilities
+            .as_ref()
+            .and_then(|probabilities| probabilities.get(&fatal).copied()),
+    };
+    let settled = deduced || probabilities.is_some();
+    let safest_guess = probabilities.and_then(|probabilities| {
+        probabilities
+            .into_iter()
+            .min_by(|(a_cell, a), (b_cell, b)| a.total_cmp(b).then(a_cell.cmp(b_cell)))
+    });
+
+    PostMortem {
+        safe_cells,
+        settled,
+        fatal_probability,
+        safest_guess,
+    }
+}
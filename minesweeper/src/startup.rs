@@ -0,0 +1,305 @@
+//! How the game starts: `startup.toml` in the config dir, then command line flags over it.
+//!
+//! ```toml
+//! board = "30x16"
+//! mines = 99
+//! seed = 1234
+//! generator = "classic"
+//! skin = "Classic"
+//! window = "600x800"
+//! screen = "play"
+//! ```
+
+use std::{fs, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    MinesweeperPlugin, VIEW_HEIGHT, VIEW_WIDTH, config,
+    minefield::{BOARD_SIDES, Variant, mine_room},
+    skin,
+};
+
+pub const USAGE: &str = "usage: minesweeper [--config <file.toml>] [--board <width>x<height>] \
+                         [--mines <count>] [--seed <number>] [--generator <mode>] \
+                         [--skin <name>] [--window <width>x<height>] [--screen <name>]";
+/// Smallest window, in logical pixels, the menus still fit.
+const MIN_WINDOW: (u32, u32) = (300, 400);
+/// Names `--generator` takes, for the mine rules of `Variant::ALL` in the same order.
+const GENERATORS: [&str; 4] = ["classic", "multi-mine", "anti-mine", "multi-anti-mine"];
+
+/// Where the game opens.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum StartScreen {
+    #[default]
+    Menu,
+    /// Straight into a random board.
+    Play,
+    Settings,
+    Records,
+    Achievements,
+    Editor,
+    Training,
+}
+
+impl StartScreen {
+    const ALL: [(&'static str, StartScreen); 7] = [
+        ("menu", StartScreen::Menu),
+        ("play", StartScreen::Play),
+        ("settings", StartScreen::Settings),
+        ("records", StartScreen::Records),
+        ("achievements", StartScreen::Achievements),
+        ("editor", StartScreen::Editor),
+        ("training", StartScreen::Training),
+    ];
+}
+
+/// `startup.toml`, every key optional and written the same way as its flag.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct StartupFile {
+    board: Option<String>,
+    mines: Option<u64>,
+    seed: Option<u64>,
+    generator: Option<String>,
+    skin: Option<String>,
+    window: Option<String>,
+    screen: Option<String>,
+}
+
+/// A setting as written and where, so errors can point at it.
+struct Setting {
+    value: String,
+    origin: String,
+}
+
+impl Setting {
+    fn error(&self, problem: impl std::fmt::Display) -> String {
+        format!("{}: {problem}, got {:?}", self.origin, self.value)
+    }
+}
+
+#[derive(Default)]
+struct Settings {
+    board: Option<Setting>,
+    mines: Option<Setting>,
+    seed: Option<Setting>,
+    generator: Option<Setting>,
+    skin: Option<Setting>,
+    window: Option<Setting>,
+    screen: Option<Setting>,
+}
+
+impl Settings {
+    fn slot(&mut self, key: &str) -> Option<&mut Option<Setting>> {
+        Some(match key {
+            "board" => &mut self.board,
+            "mines" => &mut self.mines,
+            "seed" => &mut self.seed,
+            "generator" => &mut self.generator,
+            "skin" => &mut self.skin,
+            "window" => &mut self.window,
+            "screen" => &mut self.screen,
+            _ => return None,
+        })
+    }
+
+    fn read_file(&mut self, path: &PathBuf, required: bool) -> Result<(), String> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(_) if !required => return Ok(()),
+            Err(err) => return Err(format!("Couldn't read {}: {err}", path.display())),
+        };
+        let file: StartupFile = toml::from_str(&contents)
+            .map_err(|err| format!("Couldn't read {}: {err}", path.display()))?;
+
+        let values = [
+            ("board", file.board),
+            ("mines", file.mines.map(|mines| mines.to_string())),
+            ("seed", file.seed.map(|seed| seed.to_string())),
+            ("generator", file.generator),
+            ("skin", file.skin),
+            ("window", file.window),
+            ("screen", file.screen),
+        ];
+        for (key, value) in values {
+            if let (Some(value), Some(slot)) = (value, self.slot(key)) {
+                *slot = Some(Setting {
+                    value,
+                    origin: format!("{key} in {}", path.display()),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Everything the game starts with, from the config file and the command line.
+#[derive(Debug)]
+pub struct Options {
+    pub width: usize,
+    pub height: usize,
+    pub mines: usize,
+    pub seed: Option<u64>,
+    pub variant: Variant,
+    pub skin: Option<String>,
+    /// Logical size of the window.
+    pub window: (u32, u32),
+    pub screen: StartScreen,
+}
+
+impl Options {
+    /// Reads `--config`, or `startup.toml` in the config dir when it's there, then lets the
+    /// flags in `args` override it.
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut config_file = None;
+        let mut flags = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let key = arg
+                .strip_prefix("--")
+                .ok_or_else(|| format!("unexpected argument {arg}\n{USAGE}"))?;
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{arg} needs a value\n{USAGE}"))?;
+            if key == "config" {
+                config_file = Some(PathBuf::from(value));
+            } else {
+                flags.push((key, arg, value));
+            }
+        }
+
+        let mut settings = Settings::default();
+        match &config_file {
+            Some(path) => settings.read_file(path, true)?,
+            None => {
+                settings.read_file(&config::path("startup").with_extension("toml"), false)?
+            }
+        }
+        for (key, arg, value) in flags {
+            let slot = settings
+                .slot(key)
+                .ok_or_else(|| format!("unknown flag {arg}\n{USAGE}"))?;
+            *slot = Some(Setting {
+                value: value.clone(),
+                origin: arg.clone(),
+            });
+        }
+        Options::from_settings(&settings)
+    }
+
+    fn from_settings(settings: &Settings) -> Result<Options, String> {
+        let defaults = MinesweeperPlugin::default();
+        let mut options = Options {
+            width: defaults.width,
+            height: defaults.height,
+            mines: defaults.mines,
+            seed: None,
+            variant: defaults.variant,
+            skin: None,
+            window: (VIEW_WIDTH as u32, VIEW_HEIGHT as u32),
+            screen: StartScreen::Menu,
+        };
+
+        if let Some(board) = &settings.board {
+            let (width, height) = parse_size(&board.value)
+                .ok_or_else(|| board.error("expected <width>x<height> like 30x16"))?;
+            if !BOARD_SIDES.contains(&width) || !BOARD_SIDES.contains(&height) {
+                return Err(board.error(format!(
+                    "boards are {} to {} cells a side",
+                    BOARD_SIDES.start(),
+                    BOARD_SIDES.end()
+                )));
+            }
+            (options.width, options.height) = (width, height);
+        }
+        if let Some(generator) = &settings.generator {
+            let index = GENERATORS
+                .iter()
+                .position(|name| *name == generator.value)
+                .ok_or_else(|| generator.error(format!("expected one of {GENERATORS:?}")))?;
+            options.variant = Variant::ALL[index];
+        }
+        if let Some(mines) = &settings.mines {
+            options.mines = mines
+                .value
+                .parse()
+                .ok()
+                .filter(|&mines| mines > 0)
+                .ok_or_else(|| mines.error("expected a number of mines above 0"))?;
+        }
+        let room = mine_room(options.width, options.height, options.variant);
+        if options.mines > room {
+            let problem = format!(
+                "a {}x{} board with the {} generator holds at most {room} mines",
+                options.width,
+                options.height,
+                GENERATORS[Variant::ALL
+                    .iter()
+                    .position(|variant| *variant == options.variant)
+                    .unwrap_or(0)]
+            );
+            return Err(match &settings.mines {
+                Some(mines) => mines.error(problem),
+                None => format!("the default {} mines don't fit: {problem}", options.mines),
+            });
+        }
+        if let Some(seed) = &settings.seed {
+            options.seed = Some(
+                seed.value
+                    .parse()
+                    .map_err(|_| seed.error("expected a whole number"))?,
+            );
+        }
+        if let Some(skin) = &settings.skin {
+            let names: Vec<String> = skin::available()
+                .into_iter()
+                .map(|skin| skin.name)
+                .collect();
+            if !names.contains(&skin.value) {
+                return Err(skin.error(format!("expected one of {names:?}")));
+            }
+            options.skin = Some(skin.value.clone());
+        }
+        if let Some(window) = &settings.window {
+            let (width, height) = parse_size(&window.value)
+                .ok_or_else(|| window.error("expected <width>x<height> like 600x800"))?;
+            if width < MIN_WINDOW.0 || height < MIN_WINDOW.1 {
+                return Err(window.error(format!(
+                    "the window is at least {}x{}",
+                    MIN_WINDOW.0, MIN_WINDOW.1
+                )));
+            }
+            options.window = (width, height);
+        }
+        if let Some(screen) = &settings.screen {
+            let names = StartScreen::ALL.map(|(name, _)| name);
+            options.screen = StartScreen::ALL
+                .iter()
+                .find(|(name, _)| *name == screen.value)
+                .map(|&(_, screen)| screen)
+                .ok_or_else(|| screen.error(format!("expected one of {names:?}")))?;
+        }
+        Ok(options)
+    }
+
+    pub fn plugin(&self) -> MinesweeperPlugin {
+        MinesweeperPlugin {
+            width: self.width,
+            height: self.height,
+            mines: self.mines,
+            variant: self.variant,
+            seed: self.seed,
+            skin: self.skin.clone(),
+            screen: self.screen,
+            ..default()
+        }
+    }
+}
+
+/// `30x16` as `(30, 16)`.
+fn parse_size<T: std::str::FromStr>(size: &str) -> Option<(T, T)> {
+    let (width, height) = size.split_once('x')?;
+    Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+}
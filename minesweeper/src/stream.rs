@@ -0,0 +1,631 @@
+//! Live games as newline-delimited JSON, one `Message` per line, for spectators and overlays.
+//!
+//! A game publishes once `address` is set in `stream.ron` in the config dir, either a TCP
+//! address like `127.0.0.1:7879` or, on Unix, a socket path like `unix:/tmp/minesweeper.sock`.
+//! Every spectator gets the whole board as it connects and with every new game, and in
+//! between only the cells that changed.
+
+use std::{
+    io::{self, Read, Write},
+    net::TcpListener,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ControlPanelFace, GameStates, GameTimer, NewGame, Outcome,
+    animation::{AnimationSettings, CellAnimations},
+    config, draw_control_panel,
+    events::{CellOpened, ChordPerformed, FlagToggled, GameEnded, MineHit},
+    minefield::{Cell, MineField, Stencil, Variant},
+};
+
+/// Between attempts to reach a game that isn't publishing yet or went away.
+const RECONNECT_SECONDS: f32 = 1.;
+/// Bytes a spectator may fall behind by before it's dropped, a few full boards of the
+/// largest size.
+const MAX_UNSENT: usize = 16 << 20;
+const STATUS_COLOR: Color = Color::srgb(0.6, 0.8, 1.);
+
+/// A cell as the player sees it, so spectators learn nothing the player doesn't know.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CellView {
+    Hidden,
+    /// Flags on a hidden cell, negative for anti-mine flags.
+    Flagged(i32),
+    /// An open safe cell and its number.
+    Open(i32),
+    /// An open cell with mines in it, negative for anti-mines.
+    Mine(i32),
+}
+
+impl CellView {
+    fn of(cell: &Cell) -> CellView {
+        match (cell.is_open, cell.is_mined()) {
+            (true, true) => CellView::Mine(cell.mines),
+            (true, false) => CellView::Open(cell.mines_around),
+            (false, _) if cell.is_tagged() => CellView::Flagged(cell.flags),
+            (false, _) => CellView::Hidden,
+        }
+    }
+
+    /// Enough of a cell to draw it, hidden ones never hold mines.
+    fn cell(self) -> Cell {
+        match self {
+            CellView::Hidden => Cell::new(0, false, 0, 0, 0),
+            CellView::Flagged(flags) => Cell::new(0, false, flags, 0, 0),
+            CellView::Open(number) => Cell::new(0, true, 0, number, 0),
+            CellView::Mine(mines) => Cell::new(mines, true, 0, 0, 0),
+        }
+    }
+}
+
+/// One line of the stream.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    /// A fresh board was dealt, the next `Board` shows it.
+    NewGame,
+    /// The whole board, sent to each spectator as it connects and after every `NewGame`.
+    /// `cells` go first row first. `variant` names the rules the other fields spell out.
+    Board {
+        width: usize,
+        height: usize,
+        variant: String,
+        max_mines_per_cell: i32,
+        anti_mines: bool,
+        stencil: Stencil,
+        time: f32,
+        cells: Vec<Vec<CellView>>,
+    },
+    /// The cells that look different since the last `Board` or `Cells`.
+    Cells {
+        time: f32,
+        changed: Vec<CellChange>,
+    },
+    CellOpened {
+        row: usize,
+        column: usize,
+        opened: Vec<(usize, usize)>,
+    },
+    Chord {
+        row: usize,
+        column: usize,
+        opened: Vec<(usize, usize)>,
+    },
+    Flag {
+        row: usize,
+        column: usize,
+        flags: i32,
+    },
+    MineHit {
+        row: usize,
+        column: usize,
+    },
+    GameEnded {
+        won: bool,
+        time: f32,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct CellChange {
+    pub row: usize,
+    pub column: usize,
+    pub cell: CellView,
+}
+
+fn views(minefield: &MineField) -> Vec<Vec<CellView>> {
+    minefield
+        .cells
+        .iter()
+        .map(|row| row.iter().map(CellView::of).collect())
+        .collect()
+}
+
+impl Message {
+    fn board(minefield: &MineField, time: f32) -> Message {
+        Message::Board {
+            width: minefield.width,
+            height: minefield.height,
+            variant: minefield.variant.name(),
+            max_mines_per_cell: minefield.variant.max_mines_per_cell,
+            anti_mines: minefield.variant.anti_mines,
+            stencil: minefield.variant.stencil,
+            time,
+            cells: views(minefield),
+        }
+    }
+
+    /// The rules a `Board` was dealt with.
+    pub fn variant(&self) -> Option<Variant> {
+        match *self {
+            Message::Board {
+                max_mines_per_cell,
+                anti_mines,
+                stencil,
+                ..
+            } => Some(Variant {
+                max_mines_per_cell,
+                anti_mines,
+                stencil,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Read from `stream.ron` in the config dir, never written by the game.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct StreamSettings {
+    address: Option<String>,
+}
+
+/// The game to watch, for a process started as a spectator.
+#[derive(Resource, Clone)]
+pub struct SpectateAddress(pub String);
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixListener),
+}
+
+/// A connected spectator and what's been sent to it but not yet taken.
+struct Connection {
+    stream: Box<dyn Write + Send + Sync>,
+    unsent: Vec<u8>,
+}
+
+impl Connection {
+    /// Writes as much of `unsent` as the socket takes without blocking. Returns `false` once
+    /// the spectator is gone or too far behind to catch up.
+    fn flush(&mut self) -> bool {
+        while !self.unsent.is_empty() {
+            match self.stream.write(&self.unsent) {
+                Ok(0) => return false,
+                Ok(written) => {
+                    self.unsent.drain(..written);
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => return false,
+            }
+        }
+        self.unsent.len() <= MAX_UNSENT
+    }
+}
+
+/// Where spectators connect, and the ones that have.
+#[derive(Resource)]
+pub struct Publisher {
+    listener: Listener,
+    spectators: Vec<Connection>,
+    /// The board as last published, to tell which cells changed.
+    shown: Vec<Vec<CellView>>,
+}
+
+impl Publisher {
+    pub fn bind(address: &str) -> io::Result<Publisher> {
+        let listener = match address.strip_prefix("unix:") {
+            #[cfg(unix)]
+            Some(path) => {
+                // Left behind by a game that didn't get to clean up
+                let _ = std::fs::remove_file(path);
+                let listener = std::os::unix::net::UnixListener::bind(path)?;
+                listener.set_nonblocking(true)?;
+                Listener::Unix(listener)
+            }
+            #[cfg(not(unix))]
+            Some(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "unix sockets need a Unix system",
+                ));
+            }
+            None => {
+                let listener = TcpListener::bind(address)?;
+                listener.set_nonblocking(true)?;
+                Listener::Tcp(listener)
+            }
+        };
+        Ok(Publisher {
+            listener,
+            spectators: Vec::new(),
+            shown: Vec::new(),
+        })
+    }
+
+    /// The bound TCP port, handy when binding to port 0.
+    pub fn port(&self) -> Option<u16> {
+        match &self.listener {
+            Listener::Tcp(listener) => {
+                listener.local_addr().ok().map(|address| address.port())
+            }
+            #[cfg(unix)]
+            Listener::Unix(_) => None,
+        }
+    }
+
+    fn accept(&self) -> io::Result<Box<dyn Write + Send + Sync>> {
+        match &self.listener {
+            Listener::Tcp(listener) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nodelay(true)?;
+                stream.set_nonblocking(true)?;
+                Ok(Box::new(stream))
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nonblocking(true)?;
+                Ok(Box::new(stream))
+            }
+        }
+    }
+
+    /// Queues `lines` for every spectator.
+    fn send(&mut self, lines: &str) {
+        for spectator in &mut self.spectators {
+            spectator.unsent.extend_from_slice(lines.as_bytes());
+        }
+    }
+
+    /// Sends what the spectators will take without blocking the game, dropping the ones
+    /// that went away or fell too far behind.
+    fn flush(&mut self) {
+        self.spectators.retain_mut(Connection::flush);
+    }
+
+    /// The board's cells that look different from when it was last published, `None` when
+    /// it's a different board altogether.
+    fn changes(&mut self, minefield: &MineField) -> Option<Vec<CellChange>> {
+        let views = views(minefield);
+        let shown = std::mem::replace(&mut self.shown, views);
+        if shown.len() != minefield.height
+            || shown.iter().any(|row| row.len() != minefield.width)
+        {
+            return None;
+        }
+        Some(
+            shown
+                .iter()
+                .zip(&self.shown)
+                .enumerate()
+                .flat_map(|(row, (before, after))| {
+                    before
+                        .iter()
+                        .zip(after)
+                        .enumerate()
+                        .filter(|(_, (before, after))| before != after)
+                        .map(move |(column, (_, &cell))| CellChange { row, column, cell })
+                })
+                .collect(),
+        )
+    }
+}
+
+fn line(message: &Message) -> String {
+    // Only strings and numbers go in, so this can't fail
+    serde_json::to_string(message).unwrap_or_default() + "\n"
+}
+
+/// A spectator's connection to the game it watches.
+#[derive(Resource)]
+struct Spectator {
+    stream: Option<Box<dyn Read + Send + Sync>>,
+    /// Bytes read past the last complete line.
+    pending: Vec<u8>,
+    retry: Timer,
+    /// How the game being watched ended, `None` while it's still on.
+    outcome: Option<Outcome>,
+}
+
+pub(crate) fn plugin(app: &mut App) {
+    if app.world().contains_resource::<SpectateAddress>() {
+        app.insert_resource(Spectator {
+            stream: None,
+            pending: Vec::new(),
+            retry: Timer::from_seconds(RECONNECT_SECONDS, TimerMode::Repeating),
+            outcome: None,
+        })
+        .add_systems(
+            Update,
+            (
+                (connect, watch).chain(),
+                draw_spectator_status.after(draw_control_panel),
+            )
+                .run_if(in_state(GameStates::Spectating)),
+        );
+        return;
+    }
+
+    if let Some(address) = config::load::<StreamSettings>(app, "stream").address {
+        match Publisher::bind(&address) {
+            Ok(publisher) => {
+                info!("Streaming games on {address}");
+                app.insert_resource(publisher);
+            }
+            Err(err) => warn!("Couldn't stream on {address}: {err}"),
+        }
+    }
+    // Late in the frame, so every change the game made to the board is in
+    app.add_systems(
+        PostUpdate,
+        (accept_spectators, publish)
+            .chain()
+            .run_if(resource_exists::<Publisher>),
+    );
+}
+
+/// New spectators start from the board as it is now.
+fn accept_spectators(
+    mut publisher: ResMut<Publisher>,
+    timer: Res<GameTimer>,
+    minefield_query: Query<&MineField>,
+) {
+    let mut board = None;
+    while let Ok(stream) = publisher.accept() {
+        // Only worth putting together once someone turns up
+        let board = board.get_or_insert_with(|| {
+            minefield_query
+                .single()
+                .map(|minefield| line(&Message::board(minefield, timer.0.elapsed_secs())))
+                .unwrap_or_default()
+        });
+        publisher.spectators.push(Connection {
+            stream,
+            unsent: board.clone().into_bytes(),
+        });
+    }
+}
+
+/// Everything that happened this frame, then what it changed on the board: all of it for
+/// a new game, otherwise just the cells that look different.
+fn publish(
+    mut publisher: ResMut<Publisher>,
+    timer: Res<GameTimer>,
+    minefield_query: Query<Ref<MineField>>,
+    mut new_game: EventReader<NewGame>,
+    mut cells_opened: EventReader<CellOpened>,
+    mut chords: EventReader<ChordPerformed>,
+    mut flags_toggled: EventReader<FlagToggled>,
+    mut mines_hit: EventReader<MineHit>,
+    mut ended: EventReader<GameEnded>,
+) {
+    let mut messages = Vec::new();
+    let new_game = new_game.read().count() != 0;
+    if new_game {
+        messages.push(Message::NewGame);
+    }
+    messages.extend(cells_opened.read().map(|opened| Message::CellOpened {
+        row: opened.row,
+        column: opened.column,
+        opened: opened.opened.clone(),
+    }));
+    messages.extend(chords.read().map(|chord| Message::Chord {
+        row: chord.row,
+        column: chord.column,
+        opened: chord.opened.clone(),
+    }));
+    messages.extend(flags_toggled.read().map(|flag| Message::Flag {
+        row: flag.row,
+        column: flag.column,
+        flags: flag.flags,
+    }));
+    messages.extend(mines_hit.read().map(|hit| Message::MineHit {
+        row: hit.row,
+        column: hit.column,
+    }));
+    if let Ok(minefield) = minefield_query.single()
+        && minefield.is_changed()
+    {
+        let time = timer.0.elapsed_secs();
+        match publisher.changes(&minefield) {
+            Some(changed) if !new_game => {
+                if !changed.is_empty() {
+                    messages.push(Message::Cells { time, changed });
+                }
+            }
+            _ => messages.push(Message::board(&minefield, time)),
+        }
+    }
+    messages.extend(ended.read().map(|ended| Message::GameEnded {
+        won: matches!(ended.outcome, Outcome::Won { .. }),
+        time: ended.time,
+    }));
+
+    if !messages.is_empty() {
+        let lines: String = messages.iter().map(line).collect();
+        publisher.send(&lines);
+    }
+    publisher.flush();
+}
+
+fn open_stream(address: &str) -> io::Result<Box<dyn Read + Send + Sync>> {
+    if let Some(path) = address.strip_prefix("unix:") {
+        #[cfg(unix)]
+        {
+            let stream = std::os::unix::net::UnixStream::connect(path)?;
+            stream.set_nonblocking(true)?;
+            return Ok(Box::new(stream));
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = path;
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "unix sockets need a Unix system",
+            ));
+        }
+    }
+    let address = std::net::ToSocketAddrs::to_socket_addrs(address)?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such address"))?;
+    let stream = std::net::TcpStream::connect_timeout(&address, Duration::from_secs(1))?;
+    stream.set_nonblocking(true)?;
+    Ok(Box::new(stream))
+}
+
+/// Keeps trying the game until it answers, it may well be started after the spectator.
+fn connect(time: Res<Time>, address: Res<SpectateAddress>, mut spectator: ResMut<Spectator>) {
+    if spectator.stream.is_some() || !spectator.retry.tick(time.delta()).just_finished() {
+        return;
+    }
+    match open_stream(&address.0) {
+        Ok(stream) => {
+            info!("Watching {}", address.0);
+            spectator.stream = Some(stream);
+            spectator.pending.clear();
+        }
+        Err(err) => debug!("No game on {} yet: {err}", address.0),
+    }
+}
+
+/// Reads whatever the game sent since the last frame and mirrors it on the board.
+fn watch(
+    time: Res<Time>,
+    settings: Res<AnimationSettings>,
+    mut spectator: ResMut<Spectator>,
+    mut timer: ResMut<GameTimer>,
+    mut animations: ResMut<CellAnimations>,
+    mut minefield_query: Query<&mut MineField>,
+) {
+    let spectator = spectator.as_mut();
+    let Some(stream) = &mut spectator.stream else {
+        return;
+    };
+    let mut buffer = [0; 4096];
+    loop {
+        match stream.read(&mut buffer) {
+            Ok(0) => {
+                info!("The game stopped streaming");
+                spectator.stream = None;
+                break;
+            }
+            Ok(read) => spectator.pending.extend_from_slice(&buffer[..read]),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+            Err(err) => {
+                warn!("Lost the game: {err}");
+                spectator.stream = None;
+                break;
+            }
+        }
+    }
+    if spectator.outcome.is_none() {
+        timer.0.tick(time.delta());
+    }
+
+    let Ok(mut minefield) = minefield_query.single_mut() else {
+        return;
+    };
+    let now = time.elapsed_secs();
+    while let Some(end) = spectator.pending.iter().position(|&byte| byte == b'\n') {
+        let line: Vec<u8> = spectator.pending.drain(..=end).collect();
+        let message = match serde_json::from_slice::<Message>(&line) {
+            Ok(message) => message,
+            Err(err) => {
+                warn!("Skipping a line the game sent: {err}");
+                continue;
+            }
+        };
+
+        let variant = message.variant();
+        match message {
+            Message::NewGame => {
+                animations.clear();
+                spectator.outcome = None;
+            }
+            Message::Board {
+                width,
+                height,
+                stencil,
+                time,
+                cells,
+                ..
+            } => {
+                if !stencil.is_valid() {
+                    warn!("Skipping a board with neighbours this game can't draw");
+                    continue;
+                }
+                *minefield = MineField {
+                    cells: cells
+                        .iter()
+                        .map(|row| row.iter().map(|view| view.cell()).collect())
+                        .collect(),
+                    width,
+                    height,
+                    variant: variant.unwrap_or(Variant::CLASSIC),
+                };
+                timer.0.set_elapsed(Duration::from_secs_f32(time));
+            }
+            Message::Cells { time, changed } => {
+                for change in changed {
+                    if let Some(cell) = minefield
+                        .cells
+                        .get_mut(change.row)
+                        .and_then(|cells| cells.get_mut(change.column))
+                    {
+                        *cell = change.cell.cell();
+                    }
+                }
+                timer.0.set_elapsed(Duration::from_secs_f32(time));
+            }
+            Message::CellOpened {
+                row,
+                column,
+                opened,
+            }
+            | Message::Chord {
+                row,
+                column,
+                opened,
+            } => {
+                if settings.enabled {
+                    animations.ripple((row, column), &opened, now);
+                }
+            }
+            Message::MineHit { row, column } => animations.exploded = Some((row, column)),
+            Message::GameEnded { won, time } => {
+                spectator.outcome = Some(if won {
+                    Outcome::Won {
+                        time,
+                        new_record: false,
+                    }
+                } else {
+                    Outcome::Lost
+                });
+                timer.0.set_elapsed(Duration::from_secs_f32(time));
+            }
+            Message::Flag { .. } => {}
+        }
+    }
+}
+
+/// Whether there's a game to watch and how it ended, beside the face.
+fn draw_spectator_status(
+    mut cmd: Commands,
+    address: Res<SpectateAddress>,
+    spectator: Res<Spectator>,
+) {
+    let status = match (&spectator.stream, spectator.outcome) {
+        (None, _) => format!("Waiting for\n{}", address.0),
+        (Some(_), None) => "Watching".to_string(),
+        (Some(_), Some(Outcome::Won { time, .. })) => format!("Won in {time:.1} s"),
+        (Some(_), Some(Outcome::Lost)) => "Lost".to_string(),
+    };
+    cmd.spawn((
+        Text2d::new(status),
+        TextFont {
+            font_size: 24.,
+            ..default()
+        },
+        TextColor(STATUS_COLOR),
+        Transform::from_xyz(180., 300., 2.),
+        ControlPanelFace,
+    ));
+}
@@ -0,0 +1,953 @@
+//! Plays the game without a window or a GPU: `MinimalPlugins`, a fixed board, and clicks
+//! fed straight into `ButtonInput` with `CursorWorldPos` standing in for the mouse.
+
+use std::{
+    io::{BufRead, BufReader},
+    net::TcpStream,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use bevy::{
+    audio::AudioSource,
+    gizmos::{AppGizmoBuilder, GizmoAsset, config::DefaultGizmoConfigGroup},
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+    state::app::StatesPlugin,
+    time::TimeUpdateStrategy,
+};
+
+use crate::{
+    BoardSettings, BoardSource, CameraView, CursorWorldPos, GameStates, GameTimer,
+    MinesweeperPlugin, NewGame, Outcome,
+    achievements::{ACHIEVEMENTS, Achievements},
+    cell_position,
+    challenge::{Challenge, ChallengeMode},
+    config::ConfigDir,
+    export,
+    ghost::{BestRuns, GhostRace},
+    hot_seat::{HotSeat, HotSeatSettings},
+    leaderboard::{Game, Move},
+    minefield::{MineField, Stencil, Variant, reveal_cells},
+    modifiers::Modifiers,
+    recording::ReplayLog,
+    records::Records,
+    solver,
+    startup::{Options, StartScreen},
+    stream::{CellView, Message, Publisher},
+};
+
+/// Numbers each harness's config dir, so no two games share saved files.
+static HARNESSES: AtomicUsize = AtomicUsize::new(0);
+
+struct Harness(App);
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        if let Some(config_dir) = self.0.world().get_resource::<ConfigDir>() {
+            let _ = std::fs::remove_dir_all(&config_dir.0);
+        }
+    }
+}
+
+impl Harness {
+    /// A game on `rows`, first row first, `*` for a mine, `-` for an anti-mine and `.` for a
+    /// safe cell.
+    fn new(rows: &[&str]) -> Harness {
+        Harness::with_variant(rows, Variant::CLASSIC)
+    }
+
+    fn with_variant(rows: &[&str], variant: Variant) -> Harness {
+        // Settings and records the game saves go to a scratch dir of its own, not the
+        // player's
+        let config_dir = std::env::temp_dir().join(format!(
+            "minesweeper-tests-{}-{}",
+            std::process::id(),
+            HARNESSES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&config_dir);
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin, AssetPlugin::default()))
+            .init_asset::<Image>()
+            .init_asset::<AudioSource>()
+            .init_asset::<GizmoAsset>()
+            .init_gizmo_group::<DefaultGizmoConfigGroup>()
+            .init_resource::<ButtonInput<MouseButton>>()
+            .init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<Touches>()
+            .add_event::<MouseWheel>()
+            .insert_resource(ConfigDir(config_dir))
+            .add_plugins(MinesweeperPlugin::default());
+
+        let mines = rows
+            .iter()
+            .map(|row| {
+                row.chars()
+                    .map(|cell| match cell {
+                        '*' => 1,
+                        '-' => -1,
+                        _ => 0,
+                    })
+                    .collect()
+            })
+            .collect();
+        app.insert_resource(BoardSource::Custom { mines, variant });
+        app.update();
+        app.world_mut().send_event(NewGame);
+
+        let mut harness = Harness(app);
+        harness.frames(2);
+        assert_eq!(harness.state(), GameStates::Playing);
+        harness
+    }
+
+    fn frames(&mut self, count: usize) {
+        for _ in 0..count {
+            self.0.update();
+        }
+    }
+
+    fn click(&mut self, button: MouseButton, row: usize, column: usize) {
+        let position = cell_position(self.minefield(), row, column);
+        self.click_at(button, position);
+    }
+
+    /// Presses and releases `button` over `position`, then lets a frame pass for any state
+    /// change the click led to.
+    fn click_at(&mut self, button: MouseButton, position: Vec2) {
+        let world = self.0.world_mut();
+        world.resource_mut::<CursorWorldPos>().0 = Some(position);
+        world
+            .resource_mut::<ButtonInput<MouseButton>>()
+            .press(button);
+        self.0.update();
+
+        let mut input = self
+            .0
+            .world_mut()
+            .resource_mut::<ButtonInput<MouseButton>>();
+        input.release(button);
+        input.clear();
+        self.0.update();
+    }
+
+    fn hot_seat(&mut self, players: usize, eliminate: bool) {
+        let settings = HotSeatSettings { players, eliminate };
+        self.0
+            .world_mut()
+            .resource_mut::<HotSeat>()
+            .start(&settings);
+    }
+
+    fn scores(&self) -> Vec<i32> {
+        let hot_seat = self.0.world().resource::<HotSeat>();
+        hot_seat.players.iter().map(|player| player.score).collect()
+    }
+
+    fn turn(&self) -> usize {
+        self.0.world().resource::<HotSeat>().turn
+    }
+
+    fn challenge(&mut self) -> &mut Challenge {
+        self.0.world_mut().resource_mut::<Challenge>().into_inner()
+    }
+
+    fn mines(&mut self) -> Vec<(usize, usize)> {
+        let minefield = self.minefield();
+        (0..minefield.height)
+            .flat_map(|row| (0..minefield.width).map(move |column| (row, column)))
+            .filter(|&(row, column)| minefield.cells[row][column].is_mined())
+            .collect()
+    }
+
+    /// Opens safe cells one click at a time until the game is over.
+    fn clear_board(&mut self) {
+        while self.state() == GameStates::Playing {
+            let minefield = self.minefield();
+            let hidden = (0..minefield.height)
+                .flat_map(|row| (0..minefield.width).map(move |column| (row, column)))
+                .find(|&(row, column)| {
+                    let cell = &minefield.cells[row][column];
+                    !cell.is_open && !cell.is_mined()
+                });
+            let (row, column) = hidden.unwrap();
+            self.click(MouseButton::Left, row, column);
+        }
+    }
+
+    fn minefield(&mut self) -> &MineField {
+        let mut query = self.0.world_mut().query::<&MineField>();
+        query.single(self.0.world()).unwrap()
+    }
+
+    fn open(&mut self, row: usize, column: usize) -> bool {
+        self.minefield().cells[row][column].is_open
+    }
+
+    fn camera_scale(&mut self) -> f32 {
+        let mut query = self.0.world_mut().query::<&Projection>();
+        let Projection::Orthographic(projection) = query.single(self.0.world()).unwrap()
+        else {
+            panic!("the camera is orthographic");
+        };
+        projection.scale
+    }
+
+    fn state(&self) -> GameStates {
+        self.0.world().resource::<State<GameStates>>().get().clone()
+    }
+
+    fn outcome(&self) -> Outcome {
+        *self.0.world().resource::<Outcome>()
+    }
+}
+
+#[test]
+fn flood_fill_stops_at_numbers() {
+    let mut game = Harness::new(&["..*..", "..*..", "..*..", "..*..", "..*.."]);
+
+    game.click(MouseButton::Left, 0, 0);
+
+    for row in 0..5 {
+        assert!(game.open(row, 0) && game.open(row, 1));
+        assert!(!game.open(row, 2) && !game.open(row, 3) && !game.open(row, 4));
+    }
+    assert_eq!(game.state(), GameStates::Playing);
+}
+
+#[test]
+fn flood_fill_clearing_the_board_wins() {
+    let mut game = Harness::new(&["....", "....", "....", "...*"]);
+
+    game.click(MouseButton::Left, 0, 0);
+
+    assert!(!game.open(3, 3));
+    assert_eq!(game.state(), GameStates::GameOver);
+    assert!(matches!(game.outcome(), Outcome::Won { .. }));
+}
+
+#[test]
+fn only_random_boards_set_records() {
+    let mut game = Harness::new(&["....", "....", "....", "...*"]);
+    game.click(MouseButton::Left, 0, 0);
+    assert!(matches!(
+        game.outcome(),
+        Outcome::Won {
+            new_record: false,
+            ..
+        }
+    ));
+
+    game.0.insert_resource(BoardSource::Random);
+    game.0.world_mut().send_event(NewGame);
+    game.frames(2);
+    game.clear_board();
+    assert!(matches!(
+        game.outcome(),
+        Outcome::Won {
+            new_record: true,
+            ..
+        }
+    ));
+
+    let variant = game.minefield().variant;
+    let world = game.0.world();
+    assert!(
+        world
+            .resource::<Records>()
+            .best_time(world.resource::<BoardSettings>(), variant)
+            .is_some()
+    );
+}
+
+#[test]
+fn ghosts_are_kept_only_for_boards_that_can_come_back() {
+    let mut game = Harness::new(&["....", "....", "....", "...*"]);
+    let layout = game.0.world().resource::<ReplayLog>().0.layout();
+    game.click(MouseButton::Left, 0, 0);
+    assert!(
+        game.0
+            .world()
+            .resource::<BestRuns>()
+            .0
+            .contains_key(&layout)
+    );
+
+    game.0.insert_resource(BoardSource::Random);
+    game.0.world_mut().send_event(NewGame);
+    game.frames(2);
+    let layout = game.0.world().resource::<ReplayLog>().0.layout();
+    game.clear_board();
+    assert!(matches!(game.outcome(), Outcome::Won { .. }));
+    assert!(
+        !game
+            .0
+            .world()
+            .resource::<BestRuns>()
+            .0
+            .contains_key(&layout)
+    );
+
+    // Until "Same board" deals it again, which races the win just made
+    let time = match game.outcome() {
+        Outcome::Won { time, .. } => time,
+        _ => unreachable!(),
+    };
+    let minefield = game.minefield();
+    let same_board = BoardSource::Custom {
+        mines: minefield.mines(),
+        variant: minefield.variant,
+    };
+    game.0.insert_resource(same_board);
+    game.0.world_mut().send_event(NewGame);
+    game.frames(2);
+    let race = game.0.world().resource::<GhostRace>();
+    assert_eq!(race.best.as_ref().map(|best| best.time), Some(time));
+}
+
+#[test]
+fn chord_with_the_wrong_flag_sets_off_the_mine() {
+    let mut game = Harness::new(&["*...", "....", "....", "...."]);
+
+    game.click(MouseButton::Left, 1, 1);
+    assert!(game.open(1, 1));
+    assert!(!game.open(0, 1));
+
+    game.click(MouseButton::Right, 0, 1);
+    game.click(MouseButton::Left, 1, 1);
+
+    assert!(game.open(0, 0));
+    assert_eq!(game.state(), GameStates::GameOver);
+    assert_eq!(game.outcome(), Outcome::Lost);
+}
+
+#[test]
+fn chord_with_the_right_flag_opens_the_neighbours_and_cascades() {
+    let mut game = Harness::new(&["*...", "....", "....", "...*"]);
+
+    game.click(MouseButton::Left, 1, 1);
+    game.click(MouseButton::Right, 0, 0);
+    game.click(MouseButton::Left, 1, 1);
+
+    for (row, column) in [(0, 1), (0, 2), (1, 0), (1, 2), (2, 0), (2, 1), (2, 2)] {
+        assert!(game.open(row, column));
+    }
+    // The zeros among them cascade over the rest of the board
+    assert!(!game.open(0, 0) && !game.open(3, 3));
+    assert_eq!(game.state(), GameStates::GameOver);
+    assert!(matches!(game.outcome(), Outcome::Won { .. }));
+}
+
+#[test]
+fn a_zero_hiding_cancelled_out_mines_is_never_chorded() {
+    let mut game = Harness::with_variant(&["*.-", "...", "..."], Variant::ALL[2]);
+
+    game.click(MouseButton::Left, 1, 1);
+    assert!(game.open(1, 1));
+    assert_eq!(game.minefield().cells[1][1].mines_around, 0);
+
+    game.click(MouseButton::Left, 1, 1);
+    assert!(!game.open(0, 0) && !game.open(1, 0));
+    assert_eq!(game.state(), GameStates::Playing);
+}
+
+#[test]
+fn counting_odds_gives_up_on_a_tangle_of_multi_and_anti_mines() {
+    // Every other cell of the middle row open, linking the 28 hidden cells around them into
+    // one tangle where each cell can hold 7 different counts
+    let mines: Vec<Vec<i32>> = (0..3)
+        .map(|row| {
+            (0..11)
+                .map(|column| match (row, column % 2) {
+                    (1, 1) => 0,
+                    _ => [2, -1, 0, 1][(row + column) % 4],
+                })
+                .collect()
+        })
+        .collect();
+    let mut minefield = MineField::from_mines(&mines, Variant::ALL[3]);
+    for column in (1..11).step_by(2) {
+        minefield.cells[1][column].is_open = true;
+    }
+
+    assert!(solver::mine_probabilities(&minefield, 30).is_none());
+    let report = solver::post_mortem(&minefield, (0, 0));
+    assert!(report.fatal_probability.is_none() && report.safest_guess.is_none());
+}
+
+#[test]
+fn the_post_mortem_finds_safe_cells_where_two_numbers_overlap() {
+    // The 1 and the 3 share two hidden cells, so the 3's other two are mines and the 1's
+    // other two are safe, though neither number's cells hold the other's
+    let mut minefield =
+        MineField::from_mines(&[vec![0, 1, 0, 1], vec![0, 0, 0, 1]], Variant::CLASSIC);
+    minefield.cells[1][1].is_open = true;
+    minefield.cells[1][2].is_open = true;
+    assert_eq!(minefield.cells[1][1].mines_around, 1);
+    assert_eq!(minefield.cells[1][2].mines_around, 3);
+
+    let known = solver::deduce(&minefield);
+    assert_eq!(known.get(&(0, 0)), Some(&0));
+    assert_eq!(known.get(&(0, 3)), Some(&1));
+
+    let report = solver::post_mortem(&minefield, (0, 1));
+    assert_eq!(report.safe_cells, [(0, 0), (1, 0)]);
+    assert!(!report.was_forced());
+}
+
+#[test]
+fn knight_stencil_counts_and_floods_by_knight_moves() {
+    let knight = Variant::CLASSIC.with_stencil(Stencil::KNIGHT);
+    let mut game = Harness::with_variant(&["...", "...", "..*"], knight);
+
+    game.click(MouseButton::Left, 0, 0);
+
+    assert_eq!(game.minefield().cells[0][1].mined_neighbours, 1);
+    assert_eq!(game.minefield().cells[1][0].mined_neighbours, 1);
+    // No knight move reaches the middle, so nothing cascades into it
+    assert!(!game.open(1, 1));
+    for (row, column) in [(0, 0), (0, 1), (0, 2), (1, 0), (1, 2), (2, 0), (2, 1)] {
+        assert!(game.open(row, column));
+    }
+    assert_eq!(game.state(), GameStates::Playing);
+
+    game.click(MouseButton::Left, 1, 1);
+    assert!(matches!(game.outcome(), Outcome::Won { .. }));
+}
+
+#[test]
+fn right_click_toggles_a_flag_that_blocks_opening() {
+    let mut game = Harness::new(&["*.", ".."]);
+
+    game.click(MouseButton::Right, 0, 0);
+    assert_eq!(game.minefield().cells[0][0].flags, 1);
+
+    game.click(MouseButton::Left, 0, 0);
+    assert!(!game.open(0, 0));
+    assert_eq!(game.state(), GameStates::Playing);
+
+    game.click(MouseButton::Right, 0, 0);
+    assert_eq!(game.minefield().cells[0][0].flags, 0);
+
+    game.click(MouseButton::Left, 1, 1);
+    game.click(MouseButton::Right, 1, 1);
+    assert_eq!(game.minefield().cells[1][1].flags, 0);
+}
+
+#[test]
+fn clicking_the_face_resets_the_board() {
+    let mut game = Harness::new(&["..*..", "..*..", "..*..", "..*..", "..*.."]);
+    let face = Vec2::new(0., 300.);
+
+    game.click(MouseButton::Left, 0, 0);
+    game.click(MouseButton::Right, 0, 3);
+    game.click_at(MouseButton::Left, face);
+
+    assert_eq!(game.state(), GameStates::Playing);
+    assert!(
+        game.minefield()
+            .cells
+            .iter()
+            .flatten()
+            .all(|cell| !cell.is_open && cell.flags == 0)
+    );
+    assert!(game.0.world().resource::<GameTimer>().0.elapsed_secs() < 1.);
+
+    game.click(MouseButton::Left, 0, 2);
+    assert_eq!(game.state(), GameStates::GameOver);
+    game.click_at(MouseButton::Left, face);
+
+    assert_eq!(game.state(), GameStates::Playing);
+    assert!(!game.open(0, 2));
+}
+
+#[test]
+fn hot_seat_passes_the_turn_and_scores_each_move() {
+    let mut game = Harness::new(&["*.", ".*"]);
+    game.hot_seat(2, false);
+
+    game.click(MouseButton::Left, 0, 1);
+    assert_eq!((game.scores(), game.turn()), (vec![1, 0], 1));
+
+    game.click(MouseButton::Left, 0, 0);
+    assert_eq!((game.scores(), game.turn()), (vec![1, -10], 0));
+    assert_eq!(game.state(), GameStates::Playing);
+
+    game.click(MouseButton::Right, 1, 1);
+    assert_eq!(game.turn(), 1);
+    // The flag is the first player's, the second can't take it away
+    game.click(MouseButton::Right, 1, 1);
+    assert_eq!(game.minefield().cells[1][1].flags, 1);
+    assert_eq!(game.turn(), 1);
+
+    game.click(MouseButton::Left, 1, 0);
+    assert_eq!(game.state(), GameStates::GameOver);
+    let minefield = game.minefield().clone();
+    let totals = game.0.world().resource::<HotSeat>().totals(&minefield);
+    assert_eq!(totals, vec![6, -9]);
+}
+
+#[test]
+fn hot_seat_ends_once_every_player_is_eliminated() {
+    let mut game = Harness::new(&["*..", "...", "..*"]);
+    game.hot_seat(2, true);
+
+    game.click(MouseButton::Left, 0, 0);
+    assert_eq!(game.turn(), 1);
+
+    game.click(MouseButton::Left, 0, 1);
+    // Only the second player is left to move
+    assert_eq!(game.turn(), 1);
+    assert_eq!(game.state(), GameStates::Playing);
+
+    game.click(MouseButton::Left, 2, 2);
+    assert_eq!(game.state(), GameStates::GameOver);
+    assert_eq!(game.outcome(), Outcome::Lost);
+}
+
+#[test]
+fn survival_deals_denser_boards_until_the_lives_run_out() {
+    let mut game = Harness::new(&["*.", ".*"]);
+    game.challenge().start(ChallengeMode::Survival);
+
+    game.click(MouseButton::Left, 0, 0);
+    assert_eq!(game.challenge().lives, 2);
+    assert_eq!(game.state(), GameStates::Playing);
+
+    game.click(MouseButton::Left, 0, 1);
+    game.click(MouseButton::Left, 1, 0);
+    assert_eq!(game.challenge().cleared, 1);
+    assert_eq!(game.state(), GameStates::Playing);
+    // The next board is the configured size with a couple of extra mines
+    assert_eq!(game.minefield().width, 10);
+    let mines = game.mines();
+    assert_eq!(mines.len(), 15);
+
+    for &(row, column) in &mines[..2] {
+        game.click(MouseButton::Left, row, column);
+    }
+    assert_eq!(game.state(), GameStates::GameOver);
+    assert_eq!(game.outcome(), Outcome::Lost);
+    assert_eq!(game.challenge().cleared, 1);
+}
+
+#[test]
+fn running_out_of_opens_loses_the_board() {
+    let mut game = Harness::new(&["*...", "....", "....", "...*"]);
+    *game.0.world_mut().resource_mut::<Modifiers>() = Modifiers {
+        move_limit: Some(2),
+        ..default()
+    };
+
+    game.click(MouseButton::Left, 0, 1);
+    assert_eq!(game.state(), GameStates::Playing);
+    game.click(MouseButton::Left, 1, 0);
+
+    assert_eq!(game.state(), GameStates::GameOver);
+    assert_eq!(game.outcome(), Outcome::Lost);
+    assert!(game.open(3, 3));
+}
+
+#[test]
+fn cells_in_the_fog_cant_be_clicked() {
+    let mut game = Harness::new(&["....*.....", "....*....."]);
+    *game.0.world_mut().resource_mut::<Modifiers>() = Modifiers {
+        fog: Some(2),
+        ..default()
+    };
+
+    game.click(MouseButton::Left, 0, 0);
+    assert!(game.open(0, 3) && !game.open(0, 5));
+    game.click(MouseButton::Left, 0, 9);
+    game.click(MouseButton::Right, 1, 9);
+    assert!(!game.open(0, 9));
+    assert_eq!(game.minefield().cells[1][9].flags, 0);
+
+    // The edge of the sight is still in reach
+    game.click(MouseButton::Left, 0, 5);
+    assert!(game.open(0, 5) && !game.open(0, 9));
+    assert_eq!(game.state(), GameStates::Playing);
+}
+
+#[test]
+fn frontier_jump_zooms_in_and_heads_for_the_unresolved_cell() {
+    let mut game = Harness::new(&["*....", ".....", ".....", ".....", "....."]);
+    game.click(MouseButton::Left, 4, 4);
+    assert!(!game.open(0, 0));
+
+    let mut keyboard = game.0.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+    keyboard.press(KeyCode::KeyF);
+    game.frames(1);
+    let mut keyboard = game.0.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+    keyboard.release(KeyCode::KeyF);
+    keyboard.clear();
+    game.frames(3);
+
+    assert!(game.camera_scale() < 1.);
+    // Cell (0, 0) is the board's bottom left corner
+    let pan = game.0.world().resource::<CameraView>().pan;
+    assert!(pan.x < 0. && pan.y < 0.);
+}
+
+#[test]
+fn the_mouse_wheel_glides_the_zoom_in() {
+    let mut game = Harness::new(&["*....", ".....", ".....", ".....", "....."]);
+    game.0
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            16,
+        )));
+    game.frames(1);
+    game.0.world_mut().send_event(MouseWheel {
+        unit: MouseScrollUnit::Line,
+        x: 0.,
+        y: 3.,
+        window: Entity::PLACEHOLDER,
+    });
+    let target = 0.9f32.powi(3);
+
+    game.frames(1);
+    let scale = game.camera_scale();
+    assert!(target < scale && scale < 1., "{scale}");
+    game.frames(60);
+    assert_eq!(game.camera_scale(), target);
+}
+
+#[test]
+fn editor_boards_earn_no_achievements() {
+    let mut game = Harness::new(&["*.", ".."]);
+    game.click(MouseButton::Left, 1, 1);
+    game.click(MouseButton::Left, 0, 1);
+    game.click(MouseButton::Left, 1, 0);
+    game.frames(1);
+    assert!(matches!(game.outcome(), Outcome::Won { .. }));
+
+    let achievements = game.0.world().resource::<Achievements>();
+    assert!(!achievements.is_unlocked(&ACHIEVEMENTS[0]));
+    assert_eq!(achievements.streak, 0);
+}
+
+#[test]
+fn chording_the_board_clear_earns_the_chord_achievement() {
+    let mut game = Harness::new(&["*.", ".."]);
+    // Counted as a board dealt at random, the only kind achievements are earned on
+    game.0.insert_resource(BoardSource::Random);
+
+    game.click(MouseButton::Left, 1, 1);
+    game.click(MouseButton::Right, 0, 0);
+    game.click(MouseButton::Left, 1, 1);
+    game.frames(1);
+    assert!(matches!(game.outcome(), Outcome::Won { .. }));
+
+    let achievements = game.0.world().resource::<Achievements>();
+    let earned: Vec<&str> = ACHIEVEMENTS
+        .iter()
+        .filter(|achievement| achievements.is_unlocked(achievement))
+        .map(|achievement| achievement.id)
+        .collect();
+    // The 1 by the mine leaves three cells to guess between, and a flag went down
+    assert_eq!(earned, ["first-win", "chord-only"]);
+    assert_eq!(achievements.streak, 1);
+}
+
+#[test]
+fn spectators_get_the_board_and_every_move_as_json_lines() {
+    let mut game = Harness::with_variant(&["*.", ".."], Variant::ALL[3]);
+    let publisher = Publisher::bind("127.0.0.1:0").unwrap();
+    let port = publisher.port().unwrap();
+    game.0.insert_resource(publisher);
+    let spectator = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    spectator
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    game.frames(1);
+
+    game.click(MouseButton::Left, 1, 1);
+    game.click(MouseButton::Right, 0, 0);
+    game.click(MouseButton::Left, 1, 1);
+    game.frames(1);
+
+    let messages: Vec<Message> = BufReader::new(spectator)
+        .lines()
+        .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+        .take_while(|message| !matches!(message, Message::GameEnded { .. }))
+        .collect();
+    // The board each message left behind is the next one sent
+    let boards = replay_boards(&messages);
+    let board_after = |index: usize| boards[index..].iter().find_map(Clone::clone);
+    assert_eq!(board_after(0), Some(vec![vec![CellView::Hidden; 2]; 2]));
+    assert_eq!(messages[0].variant(), Some(Variant::ALL[3]));
+    let opened = messages
+        .iter()
+        .position(|message| matches!(message, Message::CellOpened { .. }))
+        .unwrap();
+    assert_eq!(
+        board_after(opened),
+        Some(vec![
+            vec![CellView::Hidden, CellView::Hidden],
+            vec![CellView::Hidden, CellView::Open(1)],
+        ])
+    );
+    let flagged = messages
+        .iter()
+        .position(|message| matches!(message, Message::Flag { .. }))
+        .unwrap();
+    assert_eq!(board_after(flagged).unwrap()[0][0], CellView::Flagged(1));
+    // Moves only send the cells they changed
+    assert!(
+        !messages[opened..]
+            .iter()
+            .any(|message| matches!(message, Message::Board { .. }))
+    );
+    assert!(messages.contains(&Message::CellOpened {
+        row: 1,
+        column: 1,
+        opened: vec![(1, 1)],
+    }));
+    assert!(messages.contains(&Message::Flag {
+        row: 0,
+        column: 0,
+        flags: 1,
+    }));
+    assert!(messages.iter().any(|message| matches!(
+        message,
+        Message::Chord {
+            row: 1,
+            column: 1,
+            ..
+        }
+    )));
+}
+
+/// The board as a spectator rebuilds it after each `Board` or `Cells` message, `None` for
+/// the other messages.
+fn replay_boards(messages: &[Message]) -> Vec<Option<Vec<Vec<CellView>>>> {
+    let mut board = Vec::new();
+    messages
+        .iter()
+        .map(|message| match message {
+            Message::Board { cells, .. } => {
+                board = cells.clone();
+                Some(board.clone())
+            }
+            Message::Cells { changed, .. } => {
+                for change in changed {
+                    board[change.row][change.column] = change.cell;
+                }
+                Some(board.clone())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn a_spectator_slower_than_the_socket_still_gets_the_whole_of_a_big_board() {
+    let mut rows = vec![".".repeat(200); 200];
+    rows[0] = format!("*{}", ".".repeat(199));
+    let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+    let mut game = Harness::new(&rows);
+    let publisher = Publisher::bind("127.0.0.1:0").unwrap();
+    let port = publisher.port().unwrap();
+    game.0.insert_resource(publisher);
+    let spectator = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    spectator
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    game.frames(1);
+    // Opens all but the mine, far more than a socket holds at once
+    game.click(MouseButton::Left, 199, 199);
+    assert_eq!(game.state(), GameStates::GameOver);
+
+    // Only read once the game is done writing, the rest goes out a frame at a time
+    let reader = std::thread::spawn(move || {
+        BufReader::new(spectator)
+            .lines()
+            .map(|line| serde_json::from_str::<Message>(&line.unwrap()).unwrap())
+            .take_while(|message| !matches!(message, Message::GameEnded { .. }))
+            .collect::<Vec<Message>>()
+    });
+    while !reader.is_finished() {
+        game.frames(1);
+    }
+    let messages = reader.join().unwrap();
+
+    let board = replay_boards(&messages)
+        .into_iter()
+        .flatten()
+        .last()
+        .unwrap();
+    assert_eq!(board[0][0], CellView::Hidden);
+    let open = board.iter().flatten();
+    assert_eq!(
+        open.filter(|cell| matches!(cell, CellView::Open(_)))
+            .count(),
+        200 * 200 - 1
+    );
+}
+
+#[test]
+fn lzw_output_decodes_back_to_the_same_pixels() {
+    let (width, height) = (96u16, 80u16);
+    // Long runs, then noise that fills the code table and has it cleared
+    let mut seed = 1u32;
+    let pixels: Vec<u8> = (0..usize::from(width) * usize::from(height))
+        .map(|index| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            if index < 2000 {
+                (index / 300) as u8
+            } else {
+                (seed >> 16) as u8
+            }
+        })
+        .collect();
+
+    // A single frame GIF with a grey for every palette index
+    let mut gif = b"GIF89a".to_vec();
+    gif.extend_from_slice(&width.to_le_bytes());
+    gif.extend_from_slice(&height.to_le_bytes());
+    gif.extend_from_slice(&[0xF7, 0, 0]);
+    gif.extend((0..=255u8).flat_map(|grey| [grey; 3]));
+    gif.extend_from_slice(&[0x2C, 0, 0, 0, 0]);
+    gif.extend_from_slice(&width.to_le_bytes());
+    gif.extend_from_slice(&height.to_le_bytes());
+    gif.extend_from_slice(&[0x00, 8]);
+    for block in export::lzw(&pixels).chunks(255) {
+        gif.push(block.len() as u8);
+        gif.extend_from_slice(block);
+    }
+    gif.extend_from_slice(&[0x00, 0x3B]);
+
+    let decoder = image::codecs::gif::GifDecoder::new(std::io::Cursor::new(gif)).unwrap();
+    let mut rgba = vec![0; image::ImageDecoder::total_bytes(&decoder) as usize];
+    image::ImageDecoder::read_image(decoder, &mut rgba).unwrap();
+    let decoded: Vec<u8> = rgba.chunks(4).map(|pixel| pixel[0]).collect();
+    assert_eq!(decoded, pixels);
+}
+
+/// `startup.toml` holding `contents`, in a file of the test's own.
+fn startup_file(name: &str, contents: &str) -> String {
+    let path =
+        std::env::temp_dir().join(format!("minesweeper-{name}-{}.toml", std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    path.display().to_string()
+}
+
+#[test]
+fn command_line_flags_override_the_startup_file() {
+    let config = startup_file(
+        "override",
+        "board = \"30x16\"\nmines = 99\nseed = 7\nscreen = \"play\"\n",
+    );
+    let args = [
+        "--config",
+        &config,
+        "--mines",
+        "50",
+        "--generator",
+        "anti-mine",
+    ];
+    let options = Options::parse(&args.map(String::from)).unwrap();
+
+    assert_eq!((options.width, options.height), (30, 16));
+    assert_eq!(options.mines, 50);
+    assert_eq!(options.seed, Some(7));
+    assert_eq!(options.variant, Variant::ALL[2]);
+    assert_eq!(options.screen, StartScreen::Play);
+}
+
+#[test]
+fn startup_options_that_dont_fit_together_are_refused() {
+    let config = startup_file("refused", "mines = 81\n");
+    let parse = |args: &[&str]| {
+        let args: Vec<String> = ["--config", config.as_str()]
+            .iter()
+            .chain(args)
+            .map(|arg| arg.to_string())
+            .collect();
+        Options::parse(&args).unwrap_err()
+    };
+
+    let err = parse(&["--board", "9x9"]);
+    assert!(err.starts_with("mines in "), "{err}");
+    assert!(err.contains("holds at most 80 mines"), "{err}");
+    let err = parse(&["--board", "9x9", "--mines", "100"]);
+    assert!(err.starts_with("--mines: "), "{err}");
+    assert!(parse(&["--board", "9by9"]).starts_with("--board: expected"));
+    assert!(parse(&["--screen", "lobby"]).starts_with("--screen: expected one of"));
+    assert!(parse(&["--speed", "2"]).starts_with("unknown flag --speed"));
+}
+
+/// A game on a seeded 9 by 9 board won by clicking every safe cell still hidden, in order.
+fn won_game() -> Game {
+    let (width, height, mines, seed) = (9, 9, 10, 7);
+    let mut minefield = MineField::from_seed(width, height, mines, Variant::CLASSIC, seed);
+    let mut moves = Vec::new();
+    for row in 0..height {
+        for column in 0..width {
+            let cell = &minefield.cells[row][column];
+            if cell.is_open || cell.is_mined() {
+                continue;
+            }
+            reveal_cells(&mut minefield, column, row);
+            moves.push(Move {
+                row,
+                column,
+                flag: false,
+                time: moves.len() as f32,
+            });
+        }
+    }
+
+    Game {
+        name: "Tester".to_string(),
+        width,
+        height,
+        mines,
+        max_mines_per_cell: 1,
+        anti_mines: false,
+        stencil: Stencil::KING,
+        seed,
+        time: moves.len() as f32,
+        moves,
+    }
+}
+
+#[test]
+fn a_signed_game_verifies_and_tampering_breaks_the_signature() {
+    let submission = won_game().sign("shared secret").unwrap();
+    assert!(submission.is_signed_with("shared secret"));
+    assert!(!submission.is_signed_with("another secret"));
+    assert_eq!(submission.game.verify(), Ok(()));
+
+    let mut tampered = submission.clone();
+    tampered.game.time = 1.;
+    assert!(!tampered.is_signed_with("shared secret"));
+}
+
+#[test]
+fn games_the_game_couldnt_have_played_are_refused() {
+    let refused = |change: fn(&mut Game)| {
+        let mut game = won_game();
+        change(&mut game);
+        game.verify().unwrap_err()
+    };
+
+    assert_eq!(
+        refused(|game| game.mines = 81),
+        "too many mines for the board"
+    );
+    assert_eq!(refused(|game| game.time = -1.), "moves out of order");
+    assert_eq!(refused(|game| game.moves.clear()), "no moves");
+    assert_eq!(refused(|game| game.width = 201), "board size out of range");
+    assert_eq!(
+        refused(|game| game.stencil = ron::from_str("0").unwrap()),
+        "stencil out of range"
+    );
+    assert_eq!(
+        refused(|game| {
+            game.moves.pop();
+        }),
+        "the board isn't cleared"
+    );
+}
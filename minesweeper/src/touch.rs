@@ -0,0 +1,195 @@
+use bevy::{prelude::*, ui::UiSystem};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CameraView, CursorWorldPos,
+    config::{self, ConfigDir},
+    minefield::MineField,
+    navigation::{clamp_pan, min_scale},
+};
+
+pub const LONG_PRESS_STEP: f32 = 0.1;
+/// How far in pixels a finger may slide and still count as pressing in place.
+const TAP_SLOP: f32 = 20.;
+const INDICATOR_RADIUS: f32 = 30.;
+const INDICATOR_COLOR: Color = Color::srgb(1., 1., 1.);
+const FLAGGED_INDICATOR_COLOR: Color = Color::srgb(1., 0.3, 0.);
+
+/// Saved to `touch.ron` in the config dir.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct TouchSettings {
+    /// Seconds a finger stays down before the press flags instead of opening.
+    pub long_press: f32,
+}
+
+impl TouchSettings {
+    pub fn change_long_press(&mut self, delta: f32) {
+        self.long_press = (self.long_press + delta).clamp(0.2, 2.);
+    }
+}
+
+impl Default for TouchSettings {
+    fn default() -> Self {
+        Self { long_press: 0.5 }
+    }
+}
+
+/// The one finger on the screen, while it can still become a tap or a long press.
+struct Press {
+    id: u64,
+    since: f32,
+    /// Already flagged, lifting the finger does nothing more.
+    flagged: bool,
+}
+
+#[derive(Resource, Default)]
+struct TouchPress(Option<Press>);
+
+pub fn plugin(app: &mut App) {
+    let touch_settings = config::load::<TouchSettings>(app, "touch");
+    app.init_resource::<TouchPress>()
+        .insert_resource(touch_settings)
+        .add_systems(PreUpdate, touch_presses.after(UiSystem::Focus))
+        .add_systems(
+            Update,
+            (
+                pinch_and_pan,
+                draw_press_indicator,
+                save_touch_settings.run_if(
+                    resource_changed::<TouchSettings>
+                        .and(not(resource_added::<TouchSettings>)),
+                ),
+            ),
+        );
+}
+
+/// Turns a tap into a left click and a long press into a right click, so every system
+/// reading the mouse works with fingers too. Tapping a number chords it like clicking does.
+fn touch_presses(
+    touches: Res<Touches>,
+    time: Res<Time>,
+    settings: Res<TouchSettings>,
+    ui_interactions: Query<&Interaction>,
+    mut press: ResMut<TouchPress>,
+    mut mouse_btn: ResMut<ButtonInput<MouseButton>>,
+) {
+    // A second finger means a pinch or a pan, never a click
+    if touches.iter().count() > 1 {
+        press.0 = None;
+        return;
+    }
+
+    let now = time.elapsed_secs();
+    if let Some(touch) = touches.iter_just_pressed().next() {
+        press.0 = Some(Press {
+            id: touch.id(),
+            since: now,
+            flagged: false,
+        });
+    }
+    let Some(current) = &mut press.0 else {
+        return;
+    };
+
+    let on_ui = ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None);
+    let mut click = |button| {
+        if !on_ui {
+            mouse_btn.press(button);
+            mouse_btn.release(button);
+        }
+    };
+
+    if let Some(touch) = touches.get_pressed(current.id) {
+        if touch.distance().length() > TAP_SLOP {
+            press.0 = None;
+        } else if !current.flagged && now - current.since >= settings.long_press {
+            current.flagged = true;
+            click(MouseButton::Right);
+        }
+    } else {
+        if touches.just_released(current.id) && !current.flagged {
+            click(MouseButton::Left);
+        }
+        press.0 = None;
+    }
+}
+
+/// Two fingers zoom around their midpoint and drag the board along with them.
+fn pinch_and_pan(
+    touches: Res<Touches>,
+    mut view: ResMut<CameraView>,
+    mut camera_query: Query<(&Camera, &GlobalTransform, &mut Projection)>,
+    minefield_query: Query<&MineField>,
+) {
+    let mut fingers = touches.iter();
+    let (Some(first), Some(second), None) = (fingers.next(), fingers.next(), fingers.next())
+    else {
+        return;
+    };
+    let (Ok((camera, camera_transform, mut projection)), Ok(minefield)) =
+        (camera_query.single_mut(), minefield_query.single())
+    else {
+        return;
+    };
+    let Projection::Orthographic(projection) = &mut *projection else {
+        return;
+    };
+
+    let to_world = |position| camera.viewport_to_world_2d(camera_transform, position).ok();
+    let (Some(first_now), Some(second_now), Some(first_before), Some(second_before)) = (
+        to_world(first.position()),
+        to_world(second.position()),
+        to_world(first.previous_position()),
+        to_world(second.previous_position()),
+    ) else {
+        return;
+    };
+
+    let spread = first_now.distance(second_now);
+    let scale = if spread > 0. {
+        (projection.scale * first_before.distance(second_before) / spread)
+            .clamp(min_scale(minefield), 1.)
+    } else {
+        projection.scale
+    };
+    let middle = (first_now + second_now) / 2.;
+    let middle_before = (first_before + second_before) / 2.;
+
+    // Zooming keeps the point between the fingers in place, then the camera follows them
+    let pan =
+        middle + (view.pan - middle) * (scale / projection.scale) + middle_before - middle;
+    view.pan = clamp_pan(pan, scale);
+    projection.scale = scale;
+}
+
+/// A ring under the finger that closes as the press nears a flag.
+fn draw_press_indicator(
+    mut gizmos: Gizmos,
+    time: Res<Time>,
+    settings: Res<TouchSettings>,
+    press: Res<TouchPress>,
+    cursor_world_pos: Res<CursorWorldPos>,
+) {
+    let (Some(press), Some(position)) = (&press.0, cursor_world_pos.0) else {
+        return;
+    };
+
+    if press.flagged {
+        gizmos.circle_2d(position, INDICATOR_RADIUS, FLAGGED_INDICATOR_COLOR);
+    } else {
+        let progress = ((time.elapsed_secs() - press.since) / settings.long_press).min(1.);
+        gizmos.arc_2d(
+            position,
+            progress * std::f32::consts::TAU,
+            INDICATOR_RADIUS,
+            INDICATOR_COLOR,
+        );
+    }
+}
+
+fn save_touch_settings(settings: Res<TouchSettings>, config: Res<ConfigDir>) {
+    config.save("touch", &*settings);
+}
@@ -0,0 +1,410 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CursorWorldPos, GameStates, GameTimer,
+    accessibility::{Announcement, describe_cell},
+    animation::CellAnimations,
+    audio::SoundEffect,
+    cell_position, cell_size,
+    config::{self, ConfigDir},
+    find_cell_under_cursor, menu,
+    minefield::MineField,
+    patterns::{Drill, Pattern},
+};
+
+const MISTAKE_COLOR: Color = Color::srgba(1., 0., 0., 0.5);
+/// Results kept for each pattern, the oldest go first.
+const HISTORY_LENGTH: usize = 200;
+/// How many of the latest results count as current form.
+const RECENT_DRILLS: usize = 10;
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct DrillResult {
+    /// Cells marked right the first time.
+    correct: u32,
+    /// Wrong marks, including ones on cells the numbers say nothing about.
+    wrong: u32,
+    time: f32,
+}
+
+/// Finished drills by pattern, saved to `training.ron` in the config dir.
+#[derive(Resource, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct TrainingStats {
+    /// Keyed by `Pattern::name`, oldest first.
+    results: BTreeMap<String, Vec<DrillResult>>,
+}
+
+impl TrainingStats {
+    fn record(&mut self, pattern: Pattern, result: DrillResult, config: &ConfigDir) {
+        let results = self.results.entry(pattern.name().to_string()).or_default();
+        results.push(result);
+        if results.len() > HISTORY_LENGTH {
+            results.remove(0);
+        }
+        config.save("training", self);
+    }
+
+    pub fn drills(&self, pattern: Pattern) -> usize {
+        self.results.get(pattern.name()).map_or(0, Vec::len)
+    }
+
+    /// Share of correct marks and the average time over the latest `last` drills of
+    /// `pattern`, `None` before the first one.
+    pub fn summary(&self, pattern: Pattern, last: usize) -> Option<(f32, f32)> {
+        let results = self.results.get(pattern.name())?;
+        let recent = &results[results.len().saturating_sub(last)..];
+        if recent.is_empty() {
+            return None;
+        }
+
+        let correct: u32 = recent.iter().map(|result| result.correct).sum();
+        let marks: u32 = recent
+            .iter()
+            .map(|result| result.correct + result.wrong)
+            .sum();
+        let time: f32 = recent.iter().map(|result| result.time).sum();
+        Some((
+            correct as f32 / marks.max(1) as f32,
+            time / recent.len() as f32,
+        ))
+    }
+}
+
+/// Throws away the drill on the board and puts up a new one.
+#[derive(Event)]
+struct NextDrill;
+
+#[derive(Resource, Default)]
+struct CurrentDrill {
+    drill: Option<Drill>,
+    /// The only pattern drilled, all of them in turn when `None`.
+    focus: Option<Pattern>,
+    correct: u32,
+    wrong: u32,
+    finished: bool,
+}
+
+#[derive(Component, Clone, Copy)]
+enum TrainingAction {
+    Next,
+    NextFocus,
+    MainMenu,
+}
+
+#[derive(Component)]
+struct TrainingStatus;
+
+/// Red square over a cell marked wrong.
+#[derive(Component)]
+struct Mistake;
+
+pub fn plugin(app: &mut App) {
+    let training_stats = config::load::<TrainingStats>(app, "training");
+    app.init_resource::<CurrentDrill>()
+        .insert_resource(training_stats)
+        .add_event::<NextDrill>()
+        .add_systems(
+            OnEnter(GameStates::Training),
+            (
+                spawn_training_ui,
+                |mut next_drill: EventWriter<NextDrill>| {
+                    next_drill.write(NextDrill);
+                },
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                training_actions,
+                start_drill,
+                mark_cells,
+                tick_drill_timer,
+                update_status,
+            )
+                .chain()
+                .run_if(in_state(GameStates::Training)),
+        );
+}
+
+fn spawn_training_ui(mut cmd: Commands) {
+    cmd.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(0.),
+            width: Val::Percent(100.),
+            padding: UiRect::all(Val::Px(4.)),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(4.),
+            ..default()
+        },
+        BackgroundColor(menu::OVERLAY_COLOR),
+        // Lets `mark_cells` tell clicks on the strip from clicks on the board under it
+        Interaction::default(),
+        StateScoped(GameStates::Training),
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+            Text::default(),
+            TextFont {
+                font_size: 20.,
+                ..default()
+            },
+            TextLayout::new_with_justify(JustifyText::Center),
+            TrainingStatus,
+        ));
+        parent
+            .spawn(Node {
+                column_gap: Val::Px(4.),
+                ..default()
+            })
+            .with_children(|row| {
+                menu::small_button(row, "Next", TrainingAction::Next);
+                menu::small_button(row, "Pattern", TrainingAction::NextFocus);
+                menu::small_button(row, "Menu", TrainingAction::MainMenu);
+            });
+    });
+}
+
+fn training_actions(
+    buttons: Query<(&Interaction, &TrainingAction), Changed<Interaction>>,
+    mut current: ResMut<CurrentDrill>,
+    mut next_state: ResMut<NextState<GameStates>>,
+    mut next_drill: EventWriter<NextDrill>,
+) {
+    for (interaction, action) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match *action {
+            TrainingAction::Next => {
+                next_drill.write(NextDrill);
+            }
+            TrainingAction::NextFocus => {
+                // All patterns, then each one alone
+                current.focus = match current.focus {
+                    None => Some(Pattern::ALL[0]),
+                    Some(pattern) => Pattern::ALL
+                        .iter()
+                        .skip_while(|&&other| other != pattern)
+                        .nth(1)
+                        .copied(),
+                };
+                next_drill.write(NextDrill);
+            }
+            TrainingAction::MainMenu => next_state.set(GameStates::MainMenu),
+        }
+    }
+}
+
+fn start_drill(
+    mut cmd: Commands,
+    mut next_drill: EventReader<NextDrill>,
+    mut current: ResMut<CurrentDrill>,
+    mut minefield_query: Query<&mut MineField>,
+    mut animations: ResMut<CellAnimations>,
+    mut timer: ResMut<GameTimer>,
+    mut announcements: EventWriter<Announcement>,
+    mistakes: Query<Entity, With<Mistake>>,
+) {
+    if next_drill.read().count() == 0 {
+        return;
+    }
+    let Ok(mut minefield) = minefield_query.single_mut() else {
+        return;
+    };
+
+    let pattern = current
+        .focus
+        .unwrap_or_else(|| Pattern::ALL[rand::random_range(0..Pattern::ALL.len())]);
+    let drill = Drill::new(pattern);
+    *minefield = drill.minefield.clone();
+    announcements.write(Announcement(format!(
+        "{} drill, {} cells to mark",
+        pattern.name(),
+        drill.answers.len()
+    )));
+    *current = CurrentDrill {
+        drill: Some(drill),
+        focus: current.focus,
+        ..default()
+    };
+
+    for entity in mistakes {
+        cmd.entity(entity).despawn();
+    }
+    animations.clear();
+    timer.0.reset();
+}
+
+/// Left click says a cell is safe, right click that it's a mine. A wrong answer is
+/// counted and then put right, so the drill can go on.
+fn mark_cells(
+    mut cmd: Commands,
+    mouse_btn: Res<ButtonInput<MouseButton>>,
+    cursor_world_pos: Res<CursorWorldPos>,
+    ui_interactions: Query<&Interaction>,
+    timer: Res<GameTimer>,
+    mut current: ResMut<CurrentDrill>,
+    mut stats: ResMut<TrainingStats>,
+    config: Res<ConfigDir>,
+    mut minefield_query: Query<&mut MineField>,
+    mut sounds: EventWriter<SoundEffect>,
+    mut announcements: EventWriter<Announcement>,
+) {
+    let says_mine = if mouse_btn.just_pressed(MouseButton::Left) {
+        false
+    } else if mouse_btn.just_pressed(MouseButton::Right) {
+        true
+    } else {
+        return;
+    };
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+    let Ok(mut minefield) = minefield_query.single_mut() else {
+        return;
+    };
+    let Some((row, col)) = cursor_world_pos
+        .0
+        .and_then(|cursor_world_pos| find_cell_under_cursor(&minefield, cursor_world_pos))
+    else {
+        return;
+    };
+    let current = &mut *current;
+    let Some(drill) = current.drill.as_ref().filter(|_| !current.finished) else {
+        return;
+    };
+    let cell = &minefield.cells[row][col];
+    if cell.is_open || cell.is_tagged() {
+        return;
+    }
+
+    let answer = drill.answers.get(&(row, col)).copied();
+    if answer == Some(says_mine) {
+        current.correct += 1;
+    } else {
+        current.wrong += 1;
+        sounds.write(SoundEffect::Explosion);
+        cmd.spawn((
+            Sprite {
+                color: MISTAKE_COLOR,
+                custom_size: Some(Vec2::splat(cell_size(&minefield) * 0.9)),
+                ..default()
+            },
+            Transform::from_translation(cell_position(&minefield, row, col).extend(1.5)),
+            StateScoped(GameStates::Training),
+            Mistake,
+        ));
+    }
+
+    let cell = &mut minefield.cells[row][col];
+    match answer {
+        Some(true) => {
+            cell.flags = 1;
+            sounds.write(SoundEffect::Flag);
+        }
+        Some(false) => {
+            cell.is_open = true;
+            sounds.write(SoundEffect::Open);
+        }
+        None => {}
+    }
+    announcements.write(Announcement(format!(
+        "{}, {}",
+        describe_cell(&minefield, row, col),
+        if answer == Some(says_mine) {
+            "right"
+        } else {
+            "wrong"
+        }
+    )));
+
+    let all_marked = drill.answers.keys().all(|&(row, col)| {
+        let cell = &minefield.cells[row][col];
+        cell.is_open || cell.is_tagged()
+    });
+    if all_marked {
+        current.finished = true;
+        stats.record(
+            drill.pattern,
+            DrillResult {
+                correct: current.correct,
+                wrong: current.wrong,
+                time: timer.0.elapsed_secs(),
+            },
+            &config,
+        );
+        sounds.write(SoundEffect::Win);
+        announcements.write(Announcement(format!(
+            "{} done, {} wrong",
+            drill.pattern.name(),
+            current.wrong
+        )));
+    }
+}
+
+fn tick_drill_timer(
+    time: Res<Time>,
+    current: Res<CurrentDrill>,
+    mut timer: ResMut<GameTimer>,
+) {
+    if !current.finished {
+        timer.0.tick(time.delta());
+    }
+}
+
+fn update_status(
+    current: Res<CurrentDrill>,
+    stats: Res<TrainingStats>,
+    timer: Res<GameTimer>,
+    minefield_query: Query<&MineField>,
+    mut status: Query<&mut Text, With<TrainingStatus>>,
+) {
+    let (Some(drill), Ok(minefield), Ok(mut status)) = (
+        &current.drill,
+        minefield_query.single(),
+        status.single_mut(),
+    ) else {
+        return;
+    };
+
+    let focus = current.focus.map_or("all patterns", Pattern::name);
+    let name = drill.pattern.name();
+    status.0 = if current.finished {
+        let form = |last| {
+            stats
+                .summary(drill.pattern, last)
+                .map_or("-".to_string(), |(accuracy, time)| {
+                    format!("{:.0}% in {time:.1} s", accuracy * 100.)
+                })
+        };
+        format!(
+            "{name} done in {:.1} s, {} wrong\nAverage {}, last {RECENT_DRILLS}: {}",
+            timer.0.elapsed_secs(),
+            current.wrong,
+            form(usize::MAX),
+            form(RECENT_DRILLS),
+        )
+    } else {
+        let left = drill
+            .answers
+            .keys()
+            .filter(|&&(row, col)| {
+                let cell = &minefield.cells[row][col];
+                !cell.is_open && !cell.is_tagged()
+            })
+            .count();
+        format!(
+            "Drilling {focus}: {name}\nLeft click safe cells, right click mines, {left} to go"
+        )
+    };
+}